@@ -182,8 +182,8 @@ contract StellarBridgeFusionPlus is IStellarBridge, ReentrancyGuard, Pausable, O
         require(fillAmount > 0 && swap.filled + fillAmount <= swap.amount, "Invalid fill amount");
 
         // Verify Merkle proof for MEV protection
-        bytes32 leaf = keccak256(abi.encodePacked(msg.sender, fillAmount, nonce));
-        require(MerkleProof.verify(merkleProof, swap.merkleRoot, leaf), "Invalid merkle proof");
+        bytes32 leafData = keccak256(abi.encodePacked(msg.sender, fillAmount, nonce));
+        require(MerkleProof.verifyLeaf(merkleProof, swap.merkleRoot, leafData), "Invalid merkle proof");
 
         // Update state with atomic precision
         swap.filled += fillAmount;
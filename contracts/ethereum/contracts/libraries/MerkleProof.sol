@@ -17,6 +17,26 @@ library MerkleProof {
         return processProof(proof, leaf) == root;
     }
 
+    /**
+     * @dev Hash leaf data into a tree leaf. Leaves are double hashed so an
+     * internal node (the hash of 64 bytes) can never pass as a leaf. Matches
+     * tree version 1 of the Stellar contract.
+     */
+    function hashLeaf(bytes32 leafData) internal pure returns (bytes32) {
+        return keccak256(abi.encodePacked(leafData));
+    }
+
+    /**
+     * @dev Verify a proof for leaf data, hashing it into a leaf first
+     */
+    function verifyLeaf(
+        bytes32[] memory proof,
+        bytes32 root,
+        bytes32 leafData
+    ) internal pure returns (bool) {
+        return verify(proof, root, hashLeaf(leafData));
+    }
+
     /**
      * @dev Process a Merkle proof and return the calculated root
      */
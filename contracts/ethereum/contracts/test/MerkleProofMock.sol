@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+import "../libraries/MerkleProof.sol";
+
+contract MerkleProofMock {
+    function hashLeaf(bytes32 leafData) external pure returns (bytes32) {
+        return MerkleProof.hashLeaf(leafData);
+    }
+
+    function verifyLeaf(
+        bytes32[] calldata proof,
+        bytes32 root,
+        bytes32 leafData
+    ) external pure returns (bool) {
+        return MerkleProof.verifyLeaf(proof, root, leafData);
+    }
+}
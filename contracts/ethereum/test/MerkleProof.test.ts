@@ -0,0 +1,35 @@
+import { expect } from "chai";
+import { ethers } from "hardhat";
+import { keccak256, toUtf8Bytes } from "ethers";
+
+// Shared with the Stellar contract's merkle tests
+import vectors from "../../test-vectors/merkle.json";
+
+describe("MerkleProof", function () {
+  let merkle: any;
+
+  before(async function () {
+    const MerkleProofMock = await ethers.getContractFactory("MerkleProofMock");
+    merkle = await MerkleProofMock.deploy();
+  });
+
+  it("Should derive leaf data as documented", async function () {
+    const first = vectors.trees[0].leaves[0];
+    expect(keccak256(toUtf8Bytes("leaf-0"))).to.equal(first.leafData);
+    expect(await merkle.hashLeaf(first.leafData)).to.equal(vectors.trees[0].root);
+  });
+
+  for (const tree of vectors.trees) {
+    it(`Should verify every leaf of the ${tree.name} tree`, async function () {
+      for (const leaf of tree.leaves) {
+        expect(await merkle.verifyLeaf(leaf.proof, tree.root, leaf.leafData)).to.equal(true);
+      }
+    });
+  }
+
+  for (const vector of vectors.invalid) {
+    it(`Should reject ${vector.name}`, async function () {
+      expect(await merkle.verifyLeaf(vector.proof, vector.root, vector.leafData)).to.equal(false);
+    });
+  }
+});
@@ -0,0 +1,227 @@
+use soroban_sdk::{Address, Env, Vec};
+use crate::error::ContractError;
+use crate::types::{Analytics, Swap, TokenAnalytics};
+use crate::StorageKey;
+
+pub const SECONDS_PER_DAY: u64 = 86400;
+
+// Daily buckets stay in persistent storage for this many active days, then
+// move to temporary storage where they live out ARCHIVED_BUCKET_TTL ledgers
+pub const RECENT_BUCKETS: u32 = 30;
+pub const ARCHIVED_BUCKET_TTL: u32 = 90 * 17280;
+
+// Most buckets a single range view returns, keeping its reads (one per day plus
+// the day index) inside a transaction's read budget
+pub const MAX_BUCKET_RANGE: u64 = 31;
+
+// success_rate is completed swaps over finalized ones (completed, refunded or
+// expired) in basis points; average_completion_time is over completed swaps
+pub fn get(env: &Env) -> Analytics {
+    env.storage()
+        .instance()
+        .get(&StorageKey::Analytics)
+        .unwrap_or(Analytics {
+            total_volume: 0,
+            total_swaps: 0,
+            total_resolvers: 0,
+            success_rate: 10000,
+            average_completion_time: 0,
+            completed_swaps: 0,
+            refunded_swaps: 0,
+            expired_swaps: 0,
+            total_completion_time: 0,
+        })
+}
+
+pub fn set(env: &Env, analytics: &Analytics) {
+    env.storage().instance().set(&StorageKey::Analytics, analytics);
+}
+
+pub fn record_swap(env: &Env, token: &Address, amount: i128) -> Result<(), ContractError> {
+    let mut analytics = get(env);
+    analytics.total_swaps = checked(analytics.total_swaps.checked_add(1))?;
+    analytics.total_volume = checked(analytics.total_volume.checked_add(amount))?;
+    set(env, &analytics);
+
+    update_token(env, token, |stats| {
+        stats.swaps = stats.swaps.checked_add(1)?;
+        stats.volume = stats.volume.checked_add(amount)?;
+        Some(())
+    })
+}
+
+pub fn record_fill(env: &Env, token: &Address) -> Result<(), ContractError> {
+    update_token(env, token, |stats| {
+        stats.fills = stats.fills.checked_add(1)?;
+        Some(())
+    })
+}
+
+// Maker fees paid out as fill rewards, counted once they leave escrow
+pub fn record_fees(env: &Env, token: &Address, fee: i128) -> Result<(), ContractError> {
+    update_token(env, token, |stats| {
+        stats.fees = stats.fees.checked_add(fee)?;
+        Some(())
+    })
+}
+
+pub fn record_completion(env: &Env, swap: &Swap) -> Result<(), ContractError> {
+    let mut analytics = get(env);
+    let completion_time = checked(env.ledger().timestamp().checked_sub(swap.created_at))?;
+    analytics.completed_swaps = checked(analytics.completed_swaps.checked_add(1))?;
+    analytics.total_completion_time =
+        checked(analytics.total_completion_time.checked_add(completion_time))?;
+    analytics.average_completion_time = checked(
+        analytics
+            .total_completion_time
+            .checked_div(analytics.completed_swaps as u64),
+    )?;
+    update_success_rate(&mut analytics)?;
+    set(env, &analytics);
+    Ok(())
+}
+
+// Per-token refund counts include expiries; both return escrow to the initiator
+pub fn record_refund(env: &Env, token: &Address) -> Result<(), ContractError> {
+    let mut analytics = get(env);
+    analytics.refunded_swaps = checked(analytics.refunded_swaps.checked_add(1))?;
+    update_success_rate(&mut analytics)?;
+    set(env, &analytics);
+    record_token_refund(env, token)
+}
+
+pub fn record_expiry(env: &Env, token: &Address) -> Result<(), ContractError> {
+    let mut analytics = get(env);
+    analytics.expired_swaps = checked(analytics.expired_swaps.checked_add(1))?;
+    update_success_rate(&mut analytics)?;
+    set(env, &analytics);
+    record_token_refund(env, token)
+}
+
+fn record_token_refund(env: &Env, token: &Address) -> Result<(), ContractError> {
+    update_token(env, token, |stats| {
+        stats.refunds = stats.refunds.checked_add(1)?;
+        Some(())
+    })
+}
+
+fn empty_stats() -> TokenAnalytics {
+    TokenAnalytics {
+        volume: 0,
+        swaps: 0,
+        fills: 0,
+        refunds: 0,
+        fees: 0,
+    }
+}
+
+pub fn get_token(env: &Env, token: &Address) -> TokenAnalytics {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::TokenAnalytics(token.clone()))
+        .unwrap_or(empty_stats())
+}
+
+pub fn current_day(env: &Env) -> u64 {
+    env.ledger().timestamp() / SECONDS_PER_DAY
+}
+
+fn get_days(env: &Env, token: &Address) -> Vec<u64> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::AnalyticsDays(token.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+// Recent buckets, the ones listed in `days`, are persistent; archived ones are
+// temporary until their TTL lapses. Only the storage holding the day is read
+fn get_bucket(env: &Env, token: &Address, days: &Vec<u64>, day: u64) -> TokenAnalytics {
+    let key = StorageKey::DailyAnalytics(token.clone(), day);
+    let bucket = if days.contains(day) {
+        env.storage().persistent().get(&key)
+    } else {
+        env.storage().temporary().get(&key)
+    };
+    bucket.unwrap_or(empty_stats())
+}
+
+// Buckets for each day in `from_day..=to_day`, empty days included
+pub fn get_buckets(
+    env: &Env,
+    token: &Address,
+    from_day: u64,
+    to_day: u64,
+) -> Result<Vec<TokenAnalytics>, ContractError> {
+    if from_day > to_day || to_day - from_day >= MAX_BUCKET_RANGE {
+        return Err(ContractError::LimitExceeded);
+    }
+    let days = get_days(env, token);
+    let mut buckets = Vec::new(env);
+    for day in from_day..=to_day {
+        buckets.push_back(get_bucket(env, token, &days, day));
+    }
+    Ok(buckets)
+}
+
+// Apply `update` to the token's totals and today's bucket
+fn update_token(
+    env: &Env,
+    token: &Address,
+    update: impl Fn(&mut TokenAnalytics) -> Option<()>,
+) -> Result<(), ContractError> {
+    let mut totals = get_token(env, token);
+    checked(update(&mut totals))?;
+    env.storage()
+        .persistent()
+        .set(&StorageKey::TokenAnalytics(token.clone()), &totals);
+
+    let day = current_day(env);
+    let mut days = get_days(env, token);
+    let mut bucket = get_bucket(env, token, &days, day);
+    checked(update(&mut bucket))?;
+    env.storage()
+        .persistent()
+        .set(&StorageKey::DailyAnalytics(token.clone(), day), &bucket);
+    track_bucket(env, token, &mut days, day);
+    Ok(())
+}
+
+// Keep the last RECENT_BUCKETS active days persistent and archive the rest
+fn track_bucket(env: &Env, token: &Address, days: &mut Vec<u64>, day: u64) {
+    if days.last() == Some(day) {
+        return;
+    }
+
+    days.push_back(day);
+    while days.len() > RECENT_BUCKETS {
+        let old_day = days.pop_front_unchecked();
+        let key = StorageKey::DailyAnalytics(token.clone(), old_day);
+        if let Some(bucket) = env.storage().persistent().get::<_, TokenAnalytics>(&key) {
+            env.storage().persistent().remove(&key);
+            env.storage().temporary().set(&key, &bucket);
+            env.storage()
+                .temporary()
+                .extend_ttl(&key, ARCHIVED_BUCKET_TTL, ARCHIVED_BUCKET_TTL);
+        }
+    }
+    env.storage()
+        .persistent()
+        .set(&StorageKey::AnalyticsDays(token.clone()), days);
+}
+
+fn update_success_rate(analytics: &mut Analytics) -> Result<(), ContractError> {
+    let finalized = checked(
+        analytics
+            .completed_swaps
+            .checked_add(analytics.refunded_swaps)
+            .and_then(|count| count.checked_add(analytics.expired_swaps)),
+    )? as u64;
+    let completed = analytics.completed_swaps as u64;
+    analytics.success_rate =
+        checked(completed.checked_mul(10000).and_then(|rate| rate.checked_div(finalized)))? as u32;
+    Ok(())
+}
+
+fn checked<T>(value: Option<T>) -> Result<T, ContractError> {
+    value.ok_or(ContractError::CalculationOverflow)
+}
@@ -0,0 +1,239 @@
+use soroban_sdk::{BytesN, Env, Vec};
+use crate::error::ContractError;
+use crate::types::{Auction, AuctionPoint, GasBump, NetworkFee, Swap, SwapState};
+use crate::StorageKey;
+
+// Rates are expressed against this scale (10000 = 1:1)
+pub const RATE_PRECISION: i128 = 10_000;
+
+// Upper bound on intermediate curve points
+pub const MAX_CURVE_POINTS: u32 = 8;
+
+// Fee reports older than this are ignored
+pub const MAX_FEE_REPORT_AGE: u64 = 600;
+
+pub fn get_auction(env: &Env, swap_id: &BytesN<32>) -> Option<Auction> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::Auction(swap_id.clone()))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_auction(
+    env: &Env,
+    swap_id: &BytesN<32>,
+    swap: &Swap,
+    start_rate: i128,
+    end_rate: i128,
+    duration: u64,
+    points: Vec<AuctionPoint>,
+    gas_bump: Option<GasBump>,
+) -> Result<Auction, ContractError> {
+    if get_auction(env, swap_id).is_some() {
+        return Err(ContractError::AuctionAlreadyExists);
+    }
+
+    // Auctions price fills before any have landed, and multi-secret
+    // segments are released by withdraw_fill outside the auction
+    if swap.state != SwapState::Initiated || swap.secret_parts > 0 {
+        return Err(ContractError::InvalidSwapState);
+    }
+
+    // Dutch auction: the rate only ever decays towards end_rate
+    if end_rate <= 0 || start_rate < end_rate || duration == 0 {
+        return Err(ContractError::InvalidAuctionParams);
+    }
+    validate_curve(&points, start_rate, end_rate, duration)?;
+
+    let gas_bump = gas_bump.unwrap_or(GasBump {
+        gas_bump_estimate: 0,
+        gas_price_estimate: 0,
+    });
+    // A bump can at most cover the whole rate band
+    if gas_bump.gas_bump_estimate < 0
+        || gas_bump.gas_bump_estimate > start_rate - end_rate
+        || (gas_bump.gas_bump_estimate > 0 && gas_bump.gas_price_estimate <= 0)
+    {
+        return Err(ContractError::InvalidAuctionParams);
+    }
+
+    let auction = Auction {
+        start_rate,
+        end_rate,
+        start_time: env.ledger().timestamp(),
+        duration,
+        points,
+        gas_bump,
+    };
+    env.storage()
+        .persistent()
+        .set(&StorageKey::Auction(swap_id.clone()), &auction);
+
+    Ok(auction)
+}
+
+// Intermediate points must sit strictly inside the auction window, in time
+// order, within the start..end rate band and never above the previous rate
+fn validate_curve(
+    points: &Vec<AuctionPoint>,
+    start_rate: i128,
+    end_rate: i128,
+    duration: u64,
+) -> Result<(), ContractError> {
+    if points.len() > MAX_CURVE_POINTS {
+        return Err(ContractError::InvalidAuctionParams);
+    }
+
+    let mut last_offset = 0u64;
+    let mut last_rate = start_rate;
+    for point in points.iter() {
+        if point.time_offset <= last_offset || point.time_offset >= duration {
+            return Err(ContractError::InvalidAuctionParams);
+        }
+        if point.rate < end_rate || point.rate > last_rate {
+            return Err(ContractError::InvalidAuctionParams);
+        }
+        last_offset = point.time_offset;
+        last_rate = point.rate;
+    }
+
+    Ok(())
+}
+
+pub fn current_rate(env: &Env, auction: &Auction) -> i128 {
+    let elapsed = env.ledger().timestamp().saturating_sub(auction.start_time);
+    let rate = curve_rate(auction, elapsed);
+
+    // Never bump below the maker's end rate
+    (rate - gas_bump(env, auction)).max(auction.end_rate)
+}
+
+// Piecewise-linear interpolation over (0, start_rate), points..., (duration, end_rate)
+pub fn curve_rate(auction: &Auction, elapsed: u64) -> i128 {
+    if elapsed >= auction.duration {
+        return auction.end_rate;
+    }
+
+    let mut prev_offset = 0u64;
+    let mut prev_rate = auction.start_rate;
+    for point in auction.points.iter() {
+        if elapsed < point.time_offset {
+            return interpolate(prev_offset, prev_rate, point.time_offset, point.rate, elapsed);
+        }
+        prev_offset = point.time_offset;
+        prev_rate = point.rate;
+    }
+
+    interpolate(prev_offset, prev_rate, auction.duration, auction.end_rate, elapsed)
+}
+
+fn interpolate(from_offset: u64, from_rate: i128, to_offset: u64, to_rate: i128, at: u64) -> i128 {
+    let span = (to_offset - from_offset) as i128;
+    let progress = (at - from_offset) as i128;
+    from_rate + (to_rate - from_rate) * progress / span
+}
+
+// Rate reduction covering resolver fees, scaled by the oracle-reported fee;
+// saturates on extreme reports since callers clamp to the end rate anyway
+pub fn gas_bump(env: &Env, auction: &Auction) -> i128 {
+    let bump = &auction.gas_bump;
+    if bump.gas_bump_estimate == 0 {
+        return 0;
+    }
+
+    match get_network_fee(env) {
+        Some(report) => bump.gas_bump_estimate.saturating_mul(report.fee) / bump.gas_price_estimate,
+        None => 0,
+    }
+}
+
+// Latest fee report, if it is still fresh
+pub fn get_network_fee(env: &Env) -> Option<NetworkFee> {
+    let report: NetworkFee = env.storage().instance().get(&StorageKey::NetworkFee)?;
+    if env.ledger().timestamp().saturating_sub(report.updated_at) > MAX_FEE_REPORT_AGE {
+        return None;
+    }
+    Some(report)
+}
+
+pub fn taking_amount(fill_amount: i128, rate: i128) -> i128 {
+    fill_amount * rate / RATE_PRECISION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_sdk::testutils::Ledger;
+    use soroban_sdk::vec;
+
+    fn linear_auction(env: &Env) -> Auction {
+        Auction {
+            start_rate: 12_000,
+            end_rate: 10_000,
+            start_time: 1000,
+            duration: 300,
+            points: Vec::new(env),
+            gas_bump: GasBump {
+                gas_bump_estimate: 0,
+                gas_price_estimate: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_current_rate_decays_linearly() {
+        let env = Env::default();
+        env.ledger().with_mut(|li| li.timestamp = 1000);
+        let auction = linear_auction(&env);
+
+        assert_eq!(current_rate(&env, &auction), 12_000);
+
+        env.ledger().with_mut(|li| li.timestamp = 1150);
+        assert_eq!(current_rate(&env, &auction), 11_000);
+
+        env.ledger().with_mut(|li| li.timestamp = 1299);
+        assert_eq!(current_rate(&env, &auction), 10_007);
+
+        // Clamped at the end rate once the auction is over
+        env.ledger().with_mut(|li| li.timestamp = 5000);
+        assert_eq!(current_rate(&env, &auction), 10_000);
+    }
+
+    #[test]
+    fn test_curve_rate_piecewise() {
+        let env = Env::default();
+        let mut auction = linear_auction(&env);
+        // Hold near the start rate, then drop sharply, then tail off
+        auction.points = vec![
+            &env,
+            AuctionPoint { time_offset: 100, rate: 11_800 },
+            AuctionPoint { time_offset: 200, rate: 10_200 },
+        ];
+
+        assert_eq!(curve_rate(&auction, 0), 12_000);
+        assert_eq!(curve_rate(&auction, 50), 11_900);
+        assert_eq!(curve_rate(&auction, 100), 11_800);
+        assert_eq!(curve_rate(&auction, 150), 11_000);
+        assert_eq!(curve_rate(&auction, 200), 10_200);
+        assert_eq!(curve_rate(&auction, 250), 10_100);
+        assert_eq!(curve_rate(&auction, 300), 10_000);
+    }
+
+    #[test]
+    fn test_validate_curve() {
+        let env = Env::default();
+        let point = |time_offset, rate| AuctionPoint { time_offset, rate };
+
+        assert!(validate_curve(&vec![&env, point(100, 11_000)], 12_000, 10_000, 300).is_ok());
+        // Out of order or outside the window
+        assert!(validate_curve(&vec![&env, point(200, 11_000), point(100, 10_500)], 12_000, 10_000, 300).is_err());
+        assert!(validate_curve(&vec![&env, point(0, 11_000)], 12_000, 10_000, 300).is_err());
+        assert!(validate_curve(&vec![&env, point(300, 11_000)], 12_000, 10_000, 300).is_err());
+        // Outside the rate band
+        assert!(validate_curve(&vec![&env, point(100, 12_001)], 12_000, 10_000, 300).is_err());
+        assert!(validate_curve(&vec![&env, point(100, 9_999)], 12_000, 10_000, 300).is_err());
+        // The rate may hold but never rise
+        assert!(validate_curve(&vec![&env, point(100, 11_000), point(200, 11_000)], 12_000, 10_000, 300).is_ok());
+        assert!(validate_curve(&vec![&env, point(100, 10_500), point(200, 11_500)], 12_000, 10_000, 300).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use soroban_sdk::{token, Address, Env, Map};
+use crate::error::ContractError;
+use crate::resolver::{get_resolver, set_resolver, sync_active};
+use crate::rewards;
+use crate::types::{Delegation, DelegationInfo, DelegationPool, Resolver};
+use crate::{Config, StorageKey};
+
+// Scale of the per-share reward index
+pub const REWARD_INDEX_PRECISION: i128 = 1_000_000_000_000_000_000;
+
+// Own stake a resolver must register with as a share of min_stake, in basis
+// points; delegations can make up the rest
+pub const MIN_SELF_STAKE_RATE: i128 = 2_500;
+
+// Delegators hold shares of the resolver's delegated stake and of its
+// delegated unbonding stake, so slashing either one dilutes every delegator
+// proportionally without touching their records.
+pub fn get_pool(env: &Env, resolver: &Address) -> DelegationPool {
+    let mut pool = env
+        .storage()
+        .persistent()
+        .get(&StorageKey::DelegationPool(resolver.clone()))
+        .unwrap_or(DelegationPool {
+            shares: 0,
+            unbonding: 0,
+            unbonding_shares: 0,
+            commission_rate: 0,
+            pending_commission_rate: 0,
+            commission_effective_at: 0,
+            reward_index: Map::new(env),
+        });
+
+    // A scheduled commission increase applies once its delay has passed
+    if pool.commission_effective_at > 0 && env.ledger().timestamp() >= pool.commission_effective_at {
+        pool.commission_rate = pool.pending_commission_rate;
+        pool.pending_commission_rate = 0;
+        pool.commission_effective_at = 0;
+    }
+    pool
+}
+
+pub fn set_pool(env: &Env, resolver: &Address, pool: &DelegationPool) {
+    env.storage()
+        .persistent()
+        .set(&StorageKey::DelegationPool(resolver.clone()), pool);
+}
+
+pub fn get_delegation(env: &Env, delegator: &Address, resolver: &Address) -> Option<Delegation> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::Delegation(delegator.clone(), resolver.clone()))
+}
+
+fn set_delegation(env: &Env, delegator: &Address, resolver: &Address, delegation: &Delegation) {
+    let key = StorageKey::Delegation(delegator.clone(), resolver.clone());
+    if delegation.shares == 0 && delegation.unbonding_shares == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, delegation);
+    }
+}
+
+// Own plus delegated stake; what min_stake and selection weight look at
+pub fn effective_stake(data: &Resolver) -> i128 {
+    data.stake + data.delegated_stake
+}
+
+pub fn info(env: &Env, delegator: &Address, resolver: &Address) -> Option<DelegationInfo> {
+    let delegation = get_delegation(env, delegator, resolver)?;
+    let data = get_resolver(env, resolver).ok()?;
+    let pool = get_pool(env, resolver);
+    Some(DelegationInfo {
+        amount: shares_value(delegation.shares, data.delegated_stake, pool.shares),
+        unbonding_amount: shares_value(
+            delegation.unbonding_shares,
+            pool.unbonding,
+            pool.unbonding_shares,
+        ),
+        unbonding_until: delegation.unbonding_until,
+    })
+}
+
+fn shares_value(shares: i128, total: i128, total_shares: i128) -> i128 {
+    if total_shares == 0 {
+        0
+    } else {
+        shares * total / total_shares
+    }
+}
+
+// Shares minted for `amount` joining a pool; an emptied pool with
+// outstanding shares can't price new entries
+fn shares_for(amount: i128, total: i128, total_shares: i128) -> Result<i128, ContractError> {
+    let shares = if total_shares == 0 {
+        amount
+    } else if total == 0 {
+        return Err(ContractError::InsufficientStake);
+    } else {
+        amount * total_shares / total
+    };
+    if shares <= 0 {
+        return Err(ContractError::InvalidAmount);
+    }
+    Ok(shares)
+}
+
+// Rewards earned by `delegation` since its checkpoints, per reward token
+pub fn unsettled_rewards(pool: &DelegationPool, delegation: &Delegation, token: &Address) -> i128 {
+    let index = pool.reward_index.get(token.clone()).unwrap_or(0);
+    let checkpoint = delegation.reward_checkpoints.get(token.clone()).unwrap_or(0);
+    delegation.shares * (index - checkpoint) / REWARD_INDEX_PRECISION
+}
+
+// Move unsettled rewards into the delegator's claimable balances; must run
+// before the delegation's shares change
+fn settle(env: &Env, delegator: &Address, pool: &DelegationPool, delegation: &mut Delegation) {
+    for (token, index) in pool.reward_index.iter() {
+        rewards::accrue(env, delegator, &token, unsettled_rewards(pool, delegation, &token));
+        delegation.reward_checkpoints.set(token, index);
+    }
+}
+
+// Split a fill reward by effective stake: the delegated share, less the
+// resolver's commission, goes to delegators per share
+pub fn distribute_reward(
+    env: &Env,
+    resolver: &Address,
+    data: &Resolver,
+    token: &Address,
+    reward: i128,
+) {
+    if reward <= 0 {
+        return;
+    }
+
+    let mut pool = get_pool(env, resolver);
+    let effective = effective_stake(data);
+    let mut to_delegators = 0;
+    if pool.shares > 0 && effective > 0 {
+        let delegated_share = reward * data.delegated_stake / effective;
+        to_delegators = delegated_share - delegated_share * pool.commission_rate as i128 / 10000;
+    }
+
+    if to_delegators > 0 {
+        let index = pool.reward_index.get(token.clone()).unwrap_or(0);
+        pool.reward_index.set(
+            token.clone(),
+            index + to_delegators * REWARD_INDEX_PRECISION / pool.shares,
+        );
+        set_pool(env, resolver, &pool);
+    }
+    rewards::accrue(env, resolver, token, reward - to_delegators);
+}
+
+pub fn delegate(
+    env: &Env,
+    delegator: &Address,
+    resolver: &Address,
+    amount: i128,
+) -> Result<(), ContractError> {
+    if amount <= 0 {
+        return Err(ContractError::InvalidAmount);
+    }
+
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut data = get_resolver(env, resolver)?;
+    let mut pool = get_pool(env, resolver);
+    let mut delegation = get_delegation(env, delegator, resolver).unwrap_or(Delegation {
+        shares: 0,
+        unbonding_shares: 0,
+        unbonding_until: 0,
+        reward_checkpoints: Map::new(env),
+    });
+    settle(env, delegator, &pool, &mut delegation);
+
+    let shares = shares_for(amount, data.delegated_stake, pool.shares)?;
+    pool.shares += shares;
+    delegation.shares += shares;
+    data.delegated_stake += amount;
+    sync_active(&mut data, config.min_stake);
+
+    let token_client = token::Client::new(env, &config.native_token);
+    token_client.transfer(delegator, &env.current_contract_address(), &amount);
+
+    set_pool(env, resolver, &pool);
+    set_delegation(env, delegator, resolver, &delegation);
+    set_resolver(env, resolver, &data);
+    Ok(())
+}
+
+// Move delegated stake into unbonding; it stops earning but stays slashable
+pub fn undelegate(
+    env: &Env,
+    delegator: &Address,
+    resolver: &Address,
+    amount: i128,
+) -> Result<Delegation, ContractError> {
+    if amount <= 0 {
+        return Err(ContractError::InvalidAmount);
+    }
+
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut data = get_resolver(env, resolver)?;
+    let mut pool = get_pool(env, resolver);
+    let mut delegation =
+        get_delegation(env, delegator, resolver).ok_or(ContractError::InsufficientStake)?;
+    if data.delegated_stake == 0 || amount > data.delegated_stake {
+        return Err(ContractError::InsufficientStake);
+    }
+    settle(env, delegator, &pool, &mut delegation);
+
+    // Round the burned shares up so undelegating never takes more than it owns
+    let shares = (amount * pool.shares + data.delegated_stake - 1) / data.delegated_stake;
+    if shares > delegation.shares {
+        return Err(ContractError::InsufficientStake);
+    }
+    let unbonding_shares = shares_for(amount, pool.unbonding, pool.unbonding_shares)?;
+
+    pool.shares -= shares;
+    delegation.shares -= shares;
+    data.delegated_stake -= amount;
+    pool.unbonding += amount;
+    pool.unbonding_shares += unbonding_shares;
+    delegation.unbonding_shares += unbonding_shares;
+    delegation.unbonding_until = env.ledger().timestamp() + config.delegator_unbonding_period;
+    sync_active(&mut data, config.min_stake);
+
+    set_pool(env, resolver, &pool);
+    set_delegation(env, delegator, resolver, &delegation);
+    set_resolver(env, resolver, &data);
+    Ok(delegation)
+}
+
+pub fn withdraw(env: &Env, delegator: &Address, resolver: &Address) -> Result<i128, ContractError> {
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut pool = get_pool(env, resolver);
+    let mut delegation =
+        get_delegation(env, delegator, resolver).ok_or(ContractError::NoUnbondingStake)?;
+    if delegation.unbonding_shares == 0 {
+        return Err(ContractError::NoUnbondingStake);
+    }
+    if env.ledger().timestamp() < delegation.unbonding_until {
+        return Err(ContractError::UnbondingNotComplete);
+    }
+
+    let amount = shares_value(delegation.unbonding_shares, pool.unbonding, pool.unbonding_shares);
+    pool.unbonding -= amount;
+    pool.unbonding_shares -= delegation.unbonding_shares;
+    delegation.unbonding_shares = 0;
+    delegation.unbonding_until = 0;
+    set_pool(env, resolver, &pool);
+    set_delegation(env, delegator, resolver, &delegation);
+
+    if amount > 0 {
+        let token_client = token::Client::new(env, &config.native_token);
+        token_client.transfer(&env.current_contract_address(), delegator, &amount);
+    }
+    Ok(amount)
+}
+
+// Settle a delegation's rewards into its claimable balances
+pub fn settle_rewards(env: &Env, delegator: &Address, resolver: &Address) {
+    if let Some(mut delegation) = get_delegation(env, delegator, resolver) {
+        settle(env, delegator, &get_pool(env, resolver), &mut delegation);
+        set_delegation(env, delegator, resolver, &delegation);
+    }
+}
+
+// Take `amount` from delegated stake and delegated unbonding stake in
+// proportion to their sizes; the caller persists `data`
+pub fn slash(env: &Env, resolver: &Address, data: &mut Resolver, amount: i128) {
+    let mut pool = get_pool(env, resolver);
+    let delegated = data.delegated_stake + pool.unbonding;
+    if amount <= 0 || delegated == 0 {
+        return;
+    }
+
+    let from_stake = amount * data.delegated_stake / delegated;
+    data.delegated_stake -= from_stake;
+    pool.unbonding -= amount - from_stake;
+    set_pool(env, resolver, &pool);
+}
+
+// Cuts and changes to an empty pool apply at once. Increases with delegators
+// in the pool wait out delegator_unbonding_period, so anyone who objects can
+// undelegate and withdraw before the new rate applies.
+pub fn set_commission_rate(
+    env: &Env,
+    resolver: &Address,
+    commission_rate: u32,
+) -> Result<DelegationPool, ContractError> {
+    get_resolver(env, resolver)?;
+    if commission_rate > 10000 {
+        return Err(ContractError::InvalidAmount);
+    }
+
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut pool = get_pool(env, resolver);
+    if commission_rate <= pool.commission_rate || pool.shares == 0 {
+        pool.commission_rate = commission_rate;
+        pool.pending_commission_rate = 0;
+        pool.commission_effective_at = 0;
+    } else {
+        pool.pending_commission_rate = commission_rate;
+        pool.commission_effective_at =
+            env.ledger().timestamp() + config.delegator_unbonding_period.max(1);
+    }
+    set_pool(env, resolver, &pool);
+    Ok(pool)
+}
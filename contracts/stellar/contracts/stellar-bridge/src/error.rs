@@ -9,6 +9,7 @@ pub enum ContractError {
     InvalidTimelock = 2,
     InvalidSecretHash = 3,
     InvalidAddress = 4,
+    LimitExceeded = 5,
     
     // Swap state errors
     SwapNotFound = 10,
@@ -22,11 +23,17 @@ pub enum ContractError {
     InvalidSecret = 20,
     SecretAlreadyUsed = 21,
     InvalidMerkleProof = 22,
+    FillNonceAlreadyUsed = 23,
+    InvalidSecretIndex = 24,
     
     // Partial fill errors
     PartialFillsNotEnabled = 30,
     InvalidFillAmount = 31,
     ExceedsSwapAmount = 32,
+    FillBelowMinimum = 33,
+    InvalidFillStep = 34,
+    InvalidFillTerms = 35,
+    FillCountExceeded = 36,
     
     // Authorization errors
     Unauthorized = 40,
@@ -43,12 +50,26 @@ pub enum ContractError {
     ResolverAlreadyRegistered = 61,
     InsufficientStake = 62,
     ResolverNotActive = 63,
+    UnbondingNotComplete = 65,
+    NoUnbondingStake = 66,
+    NotJailed = 67,
     
     // Token errors
     InsufficientBalance = 70,
     TransferFailed = 71,
     TokenNotSupported = 72,
     
+    // Auction errors
+    AuctionNotFound = 90,
+    AuctionAlreadyExists = 91,
+    InvalidAuctionParams = 92,
+    BidBelowCurrentRate = 93,
+
+    // Reservation errors
+    ReservationExists = 100,
+    ReservationMismatch = 101,
+    InsufficientCapacity = 102,
+    
     // System errors
     ContractPaused = 80,
     SystemError = 81,
@@ -56,12 +77,6 @@ pub enum ContractError {
     CalculationOverflow = 83,
 }
 
-impl From<ContractError> for soroban_sdk::Error {
-    fn from(err: ContractError) -> Self {
-        soroban_sdk::Error::from_contract_error(err as u32)
-    }
-}
-
 // Helper functions for error handling
 impl ContractError {
     pub fn is_critical(&self) -> bool {
@@ -80,6 +95,7 @@ impl ContractError {
                 | ContractError::InvalidTimelock
                 | ContractError::InvalidSecretHash
                 | ContractError::InvalidAddress
+                | ContractError::LimitExceeded
                 | ContractError::InvalidSecret
                 | ContractError::InvalidFillAmount
         )
@@ -91,6 +107,7 @@ impl ContractError {
             ContractError::InvalidTimelock => "Timelock must be between min and max values",
             ContractError::InvalidSecretHash => "Secret hash cannot be empty",
             ContractError::InvalidAddress => "Invalid address provided",
+            ContractError::LimitExceeded => "Value exceeds the allowed limit",
             ContractError::SwapNotFound => "Swap does not exist",
             ContractError::SwapAlreadyExists => "Swap already exists",
             ContractError::SwapAlreadyCompleted => "Swap already completed",
@@ -100,9 +117,15 @@ impl ContractError {
             ContractError::InvalidSecret => "Invalid secret provided",
             ContractError::SecretAlreadyUsed => "Secret has already been used",
             ContractError::InvalidMerkleProof => "Invalid Merkle proof",
+            ContractError::FillNonceAlreadyUsed => "Fill nonce has already been used",
+            ContractError::InvalidSecretIndex => "Secret index does not match the fill amount",
             ContractError::PartialFillsNotEnabled => "Partial fills not enabled for this swap",
             ContractError::InvalidFillAmount => "Fill amount must be positive",
             ContractError::ExceedsSwapAmount => "Fill amount exceeds remaining swap amount",
+            ContractError::FillBelowMinimum => "Fill amount is below the swap minimum",
+            ContractError::InvalidFillStep => "Fill amount is not a multiple of the fill step",
+            ContractError::InvalidFillTerms => "Invalid fill mode, secret parts, minimum fill amount, fill step or exclusivity",
+            ContractError::FillCountExceeded => "Swap has reached its maximum number of fills",
             ContractError::Unauthorized => "Unauthorized operation",
             ContractError::UnauthorizedRefund => "Only initiator can refund",
             ContractError::NotActiveResolver => "Not an active resolver",
@@ -113,6 +136,9 @@ impl ContractError {
             ContractError::ResolverAlreadyRegistered => "Resolver already registered",
             ContractError::InsufficientStake => "Insufficient stake amount",
             ContractError::ResolverNotActive => "Resolver is not active",
+            ContractError::UnbondingNotComplete => "Unbonding period has not ended",
+            ContractError::NoUnbondingStake => "No stake is unbonding",
+            ContractError::NotJailed => "Resolver is not jailed",
             ContractError::InsufficientBalance => "Insufficient token balance",
             ContractError::TransferFailed => "Token transfer failed",
             ContractError::TokenNotSupported => "Token not supported",
@@ -120,6 +146,13 @@ impl ContractError {
             ContractError::SystemError => "System error occurred",
             ContractError::StorageError => "Storage error occurred",
             ContractError::CalculationOverflow => "Calculation overflow",
+            ContractError::AuctionNotFound => "No auction for this swap",
+            ContractError::AuctionAlreadyExists => "Auction already started for this swap",
+            ContractError::InvalidAuctionParams => "Auction rates must decay to a positive end rate",
+            ContractError::BidBelowCurrentRate => "Bid is below the current auction rate",
+            ContractError::ReservationExists => "Resolver already holds a reservation on this swap",
+            ContractError::ReservationMismatch => "Fill does not match the resolver's reservation",
+            ContractError::InsufficientCapacity => "Fill exceeds the swap's unreserved capacity",
         }
     }
 }
@@ -1,9 +1,9 @@
 use soroban_sdk::{Address, BytesN, Env};
-use crate::types::{Swap, SwapState};
-use crate::errors::ContractError;
+use crate::types::{FillMode, Swap, SwapState};
+use crate::error::ContractError;
 
 pub fn validate_secret(env: &Env, secret: &BytesN<32>, secret_hash: &BytesN<32>) -> bool {
-    let computed_hash = env.crypto().keccak256(secret);
+    let computed_hash = env.crypto().keccak256(&secret.clone().into());
     computed_hash == *secret_hash
 }
 
@@ -35,7 +35,7 @@ pub fn can_refund_swap(env: &Env, swap: &Swap, caller: &Address) -> Result<(), C
     // Can only refund initiated or partial filled swaps
     match swap.state {
         SwapState::Initiated | SwapState::PartialFilled => {
-            if !is_timelock_expired(env, swap.timelock) {
+            if !is_timelock_expired(env, swap.timelock) && !can_cancel_remainder(swap) {
                 Err(ContractError::TimelockNotExpired)
             } else {
                 Ok(())
@@ -47,21 +47,51 @@ pub fn can_refund_swap(env: &Env, swap: &Swap, caller: &Address) -> Result<(), C
     }
 }
 
+// Immediate-or-cancel swaps release their unfilled remainder once the first fill lands
+pub fn can_cancel_remainder(swap: &Swap) -> bool {
+    swap.fill_mode == FillMode::ImmediateOrCancel && swap.filled > 0 && swap.filled < swap.amount
+}
+
 pub fn calculate_fill_reward(fill_amount: i128, base_fee_rate: u32) -> i128 {
     // Calculate reward as percentage of fill amount
     // base_fee_rate is in basis points (10000 = 100%)
     (fill_amount * base_fee_rate as i128) / 10000
 }
 
+pub fn is_exclusive(env: &Env, swap: &Swap) -> bool {
+    !swap.exclusive_resolvers.is_empty() && env.ledger().timestamp() < swap.exclusivity_deadline
+}
+
+// Before the exclusivity deadline only the named resolvers may fill or complete
+pub fn check_exclusivity(env: &Env, swap: &Swap, resolver: &Address) -> Result<(), ContractError> {
+    if is_exclusive(env, swap) && !swap.exclusive_resolvers.contains(resolver) {
+        return Err(ContractError::Unauthorized);
+    }
+    Ok(())
+}
+
 pub fn validate_partial_fill(
     env: &Env,
     swap: &Swap,
+    fill_count: u32,
     fill_amount: i128,
     resolver: &Address,
 ) -> Result<(), ContractError> {
-    // Check if partial fills are enabled
-    if !swap.partial_fill_enabled {
-        return Err(ContractError::PartialFillsNotEnabled);
+    check_exclusivity(env, swap, resolver)?;
+
+    // Enforce the swap's fill mode
+    match swap.fill_mode {
+        FillMode::FillOrKill => {
+            if swap.filled > 0 || fill_amount != swap.amount {
+                return Err(ContractError::PartialFillsNotEnabled);
+            }
+        }
+        FillMode::AllowMultipleFills(max_fills) => {
+            if fill_count >= max_fills {
+                return Err(ContractError::FillCountExceeded);
+            }
+        }
+        FillMode::AllowPartial | FillMode::ImmediateOrCancel => {}
     }
 
     // Check swap state
@@ -84,20 +114,32 @@ pub fn validate_partial_fill(
         return Err(ContractError::ExceedsSwapAmount);
     }
 
+    // A fill that clears the remainder is exempt from size and lot rules
+    if swap.filled + fill_amount < swap.amount {
+        if fill_amount < swap.min_fill_amount {
+            return Err(ContractError::FillBelowMinimum);
+        }
+        if let Some(step) = swap.fill_step {
+            if fill_amount % step != 0 {
+                return Err(ContractError::InvalidFillStep);
+            }
+        }
+    }
+
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use soroban_sdk::testutils::{Address as _, BytesN as _};
-    use soroban_sdk::{Address, BytesN, Env};
+    use soroban_sdk::testutils::{Address as _, BytesN as _, Ledger};
+    use soroban_sdk::{Bytes, BytesN, Env, Vec};
 
     #[test]
     fn test_validate_secret() {
         let env = Env::default();
         let secret = BytesN::random(&env);
-        let secret_hash = env.crypto().keccak256(&secret);
+        let secret_hash = env.crypto().keccak256(&secret.clone().into());
         
         assert!(validate_secret(&env, &secret, &secret_hash));
         
@@ -115,6 +157,118 @@ mod tests {
         assert!(is_timelock_expired(&env, 1000));
     }
 
+    #[test]
+    fn test_validate_partial_fill_size_rules() {
+        let env = Env::default();
+        let resolver = Address::generate(&env);
+        let mut swap = Swap {
+            initiator: Address::generate(&env),
+            token: Address::generate(&env),
+            amount: 1000,
+            filled: 0,
+            secret_hash: BytesN::random(&env),
+            timelock: 100,
+            ethereum_address: Bytes::new(&env),
+            state: SwapState::Initiated,
+            fill_mode: FillMode::AllowPartial,
+            merkle_root: BytesN::random(&env),
+            merkle_version: 0,
+            created_at: 0,
+            secret_parts: 0,
+            last_secret_index: 0,
+            released: 0,
+            fee: 0,
+            min_fill_amount: 200,
+            fill_step: Some(50),
+            exclusive_resolvers: Vec::new(&env),
+            exclusivity_deadline: 0,
+        };
+
+        assert_eq!(
+            validate_partial_fill(&env, &swap, 0, 150, &resolver),
+            Err(ContractError::FillBelowMinimum)
+        );
+        assert_eq!(
+            validate_partial_fill(&env, &swap, 0, 225, &resolver),
+            Err(ContractError::InvalidFillStep)
+        );
+        assert_eq!(validate_partial_fill(&env, &swap, 0, 250, &resolver), Ok(()));
+
+        // A final fill clearing the remainder is exempt
+        swap.filled = 930;
+        assert_eq!(validate_partial_fill(&env, &swap, 1, 70, &resolver), Ok(()));
+        assert_eq!(
+            validate_partial_fill(&env, &swap, 1, 50, &resolver),
+            Err(ContractError::FillBelowMinimum)
+        );
+    }
+
+    #[test]
+    fn test_validate_partial_fill_modes() {
+        let env = Env::default();
+        let resolver = Address::generate(&env);
+        let mut swap = Swap {
+            initiator: Address::generate(&env),
+            token: Address::generate(&env),
+            amount: 1000,
+            filled: 0,
+            secret_hash: BytesN::random(&env),
+            timelock: 100,
+            ethereum_address: Bytes::new(&env),
+            state: SwapState::Initiated,
+            fill_mode: FillMode::FillOrKill,
+            merkle_root: BytesN::random(&env),
+            merkle_version: 0,
+            created_at: 0,
+            secret_parts: 0,
+            last_secret_index: 0,
+            released: 0,
+            fee: 0,
+            min_fill_amount: 0,
+            fill_step: None,
+            exclusive_resolvers: Vec::new(&env),
+            exclusivity_deadline: 0,
+        };
+
+        // Fill-or-kill only accepts a single fill for the full amount
+        assert_eq!(
+            validate_partial_fill(&env, &swap, 0, 400, &resolver),
+            Err(ContractError::PartialFillsNotEnabled)
+        );
+        assert_eq!(validate_partial_fill(&env, &swap, 0, 1000, &resolver), Ok(()));
+
+        swap.fill_mode = FillMode::AllowMultipleFills(2);
+        swap.filled = 400;
+        assert_eq!(validate_partial_fill(&env, &swap, 1, 300, &resolver), Ok(()));
+        assert_eq!(
+            validate_partial_fill(&env, &swap, 2, 300, &resolver),
+            Err(ContractError::FillCountExceeded)
+        );
+
+        // Immediate-or-cancel remainders are refundable once partly filled
+        swap.fill_mode = FillMode::ImmediateOrCancel;
+        assert!(can_refund_swap(&env, &swap, &swap.initiator).is_ok());
+        swap.filled = 0;
+        assert_eq!(
+            can_refund_swap(&env, &swap, &swap.initiator),
+            Err(ContractError::TimelockNotExpired)
+        );
+
+        // Exclusive orders only admit the named resolvers until the deadline
+        let outsider = Address::generate(&env);
+        swap.fill_mode = FillMode::AllowPartial;
+        swap.exclusive_resolvers = Vec::from_array(&env, [resolver.clone()]);
+        swap.exclusivity_deadline = 50;
+        env.ledger().with_mut(|li| li.timestamp = 49);
+        assert_eq!(validate_partial_fill(&env, &swap, 0, 300, &resolver), Ok(()));
+        assert_eq!(
+            validate_partial_fill(&env, &swap, 0, 300, &outsider),
+            Err(ContractError::Unauthorized)
+        );
+        env.ledger().with_mut(|li| li.timestamp = 50);
+        assert_eq!(validate_partial_fill(&env, &swap, 0, 300, &outsider), Ok(()));
+    }
+
     #[test]
     fn test_calculate_fill_reward() {
         // 1% fee (100 basis points)
@@ -0,0 +1,64 @@
+use soroban_sdk::{Address, Env, Symbol};
+use crate::error::ContractError;
+use crate::resolver::{get_resolver, set_resolver, sync_active};
+use crate::types::Resolver;
+use crate::{Config, StorageKey};
+
+// Failures counted in fixed windows: reaching the threshold inside one window
+// jails the resolver for the cooldown. A zero threshold disables jailing.
+pub const DEFAULT_JAIL_THRESHOLD: u32 = 3;
+pub const DEFAULT_JAIL_WINDOW: u64 = 24 * 60 * 60;
+pub const DEFAULT_JAIL_COOLDOWN: u64 = 24 * 60 * 60;
+
+// Jailed resolvers stay inactive until they unjail, even past the expiry
+pub fn is_jailed(data: &Resolver) -> bool {
+    data.jailed_until > 0
+}
+
+// Count a failure against the resolver; the caller persists `data`
+pub fn record_failure(env: &Env, resolver: &Address, data: &mut Resolver, reason: Symbol) {
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    if config.jail_threshold == 0 || is_jailed(data) {
+        return;
+    }
+
+    let now = env.ledger().timestamp();
+    if now >= data.failure_window_start + config.jail_window {
+        data.failure_window_start = now;
+        data.recent_failures = 0;
+    }
+    data.recent_failures += 1;
+    if data.recent_failures < config.jail_threshold {
+        return;
+    }
+
+    data.recent_failures = 0;
+    data.jailed_until = now + config.jail_cooldown;
+    data.jail_reason = reason.clone();
+    data.active = false;
+
+    env.events().publish(
+        (Symbol::new(env, "resolver_jailed"), resolver.clone()),
+        (reason, data.jailed_until),
+    );
+}
+
+pub fn unjail(env: &Env, resolver: &Address) -> Result<Resolver, ContractError> {
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut data = get_resolver(env, resolver)?;
+
+    if !is_jailed(&data) {
+        return Err(ContractError::NotJailed);
+    }
+    if env.ledger().timestamp() < data.jailed_until {
+        return Err(ContractError::TimelockNotExpired);
+    }
+
+    data.jailed_until = 0;
+    data.jail_reason = Symbol::new(env, "");
+    data.recent_failures = 0;
+    sync_active(&mut data, config.min_stake);
+    set_resolver(env, resolver, &data);
+
+    Ok(data)
+}
@@ -1,18 +1,32 @@
 #![no_std]
 
+mod analytics;
+mod auction;
+mod delegation;
 mod error;
 mod htlc;
+mod jailing;
 mod partial_fills;
-mod types;
+mod reputation;
+mod reservations;
+mod rewards;
+mod resolver;
+mod slashing;
+pub mod types;
 
 use soroban_sdk::{
-    contract, contractimpl, contractmeta, contracttype, token, Address, Bytes, BytesN, Env, Map,
-    Symbol, Vec,
+    contract, contractimpl, contractmeta, contracttype, token, xdr::ToXdr, Address, Bytes,
+    BytesN, Env, Map, Symbol, TryFromVal, Val, Vec,
 };
 
-use crate::error::ContractError;
-use crate::htlc::{can_complete_swap, can_refund_swap, validate_secret};
-use crate::types::{Analytics, PartialFill, Resolver, Swap, SwapState};
+pub use crate::error::ContractError;
+use crate::htlc::{can_complete_swap, can_refund_swap, is_timelock_expired, validate_secret};
+use crate::reputation::Outcome;
+use crate::types::{
+    Analytics, Auction, AuctionPoint, DelegationInfo, DelegationPool, FillMode, FillQuote,
+    FillSlice, FillTerms, FillWeighting, GasBump, LegacySwap, NetworkFee, PartialFill, Resolver,
+    ResolverProfile, ResolverSort, SlashRecord, Swap, SwapReservations, SwapState, TokenAnalytics,
+};
 
 // Contract metadata
 contractmeta!(
@@ -28,7 +42,36 @@ pub enum StorageKey {
     Swap(BytesN<32>),
     Resolver(Address),
     UsedSecret(BytesN<32>),
+    FillNonce(BytesN<32>, Address, u64),
+    PartialFill(BytesN<32>, u32),
+    FillCount(BytesN<32>),
+    PendingPayouts(BytesN<32>),
+    PendingRewards(BytesN<32>),
+    Auction(BytesN<32>),
+    Reservations(BytesN<32>),
+    // Registration order, one entry per resolver
+    ResolverIndex(u32),
+    ResolverCount,
+    ResolverApproval(Address),
+    ResolverSuspended(Address),
+    ResolverProfile(Address),
+    Operators(Address),
+    OperatorOf(Address),
+    Rewards(Address, Address),
+    DelegationPool(Address),
+    Delegation(Address, Address),
+    ActiveResolverCount,
+    Arbiter(Address),
+    InsuranceFund,
+    SlashHistory(Address),
+    FeeOracle,
+    NetworkFee,
     Analytics,
+    TokenAnalytics(Address),
+    // Token and day number (timestamp / 86400)
+    DailyAnalytics(Address, u64),
+    // Days whose buckets are still in persistent storage, oldest first
+    AnalyticsDays(Address),
     Config,
     TotalSwaps,
     Paused,
@@ -43,8 +86,25 @@ pub struct Config {
     pub max_timelock: u64,
     pub min_stake: i128,
     pub base_fee_rate: u32,
+    // Maker fee charged on top of each swap to fund fill rewards, in basis points
     pub resolver_reward_rate: u32,
     pub native_token: Address,
+    pub reservation_period: u64,
+    // Stake bonded for reserving a whole swap as a share of min_stake, in basis
+    // points; smaller reservations bond a prorated part
+    pub reservation_bond_rate: u32,
+    pub unbonding_period: u64,
+    // Share of slashed stake kept in the insurance fund, in basis points
+    pub insurance_rate: u32,
+    // Resolvers below this reputation can't take fills
+    pub min_reputation: u32,
+    // Only admin-approved addresses can register
+    pub whitelist_mode: bool,
+    pub delegator_unbonding_period: u64,
+    // Failures within jail_window that jail a resolver for jail_cooldown; 0 disables
+    pub jail_threshold: u32,
+    pub jail_window: u64,
+    pub jail_cooldown: u64,
 }
 
 #[contract]
@@ -53,6 +113,7 @@ pub struct StellarBridge;
 #[contractimpl]
 impl StellarBridge {
     // Initialize the contract
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         env: Env,
         admin: Address,
@@ -68,13 +129,23 @@ impl StellarBridge {
         }
 
         let config = Config {
-            admin,
+            admin: admin.clone(),
             min_timelock,
             max_timelock,
             min_stake,
             base_fee_rate,
             resolver_reward_rate,
             native_token,
+            reservation_period: reservations::DEFAULT_RESERVATION_PERIOD,
+            reservation_bond_rate: reservations::DEFAULT_RESERVATION_BOND_RATE,
+            unbonding_period: resolver::DEFAULT_UNBONDING_PERIOD,
+            insurance_rate: slashing::DEFAULT_INSURANCE_RATE,
+            min_reputation: 0,
+            whitelist_mode: false,
+            delegator_unbonding_period: resolver::DEFAULT_UNBONDING_PERIOD,
+            jail_threshold: jailing::DEFAULT_JAIL_THRESHOLD,
+            jail_window: jailing::DEFAULT_JAIL_WINDOW,
+            jail_cooldown: jailing::DEFAULT_JAIL_COOLDOWN,
         };
 
         env.storage().instance().set(&StorageKey::Config, &config);
@@ -82,14 +153,7 @@ impl StellarBridge {
         env.storage().instance().set(&StorageKey::Paused, &false);
 
         // Initialize analytics
-        let analytics = Analytics {
-            total_volume: 0,
-            total_swaps: 0,
-            total_resolvers: 0,
-            success_rate: 10000, // 100% in basis points
-            average_completion_time: 0,
-        };
-        env.storage().instance().set(&StorageKey::Analytics, &analytics);
+        analytics::set(&env, &analytics::get(&env));
 
         env.events().publish(
             (Symbol::new(&env, "contract_initialized"),),
@@ -100,20 +164,23 @@ impl StellarBridge {
     }
 
     // Create a new atomic swap
+    #[allow(clippy::too_many_arguments)]
     pub fn initiate_swap(
         env: Env,
+        initiator: Address,
         token: Address,
         amount: i128,
         secret_hash: BytesN<32>,
         timelock: u64,
         ethereum_address: Bytes,
-        partial_fill_enabled: bool,
         merkle_root: BytesN<32>,
+        fill_terms: FillTerms,
     ) -> Result<BytesN<32>, ContractError> {
         Self::require_not_paused(&env)?;
         
+        initiator.require_auth();
+
         let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
-        let initiator = env.current_contract_address();
 
         // Validate inputs
         if amount <= 0 {
@@ -125,49 +192,61 @@ impl StellarBridge {
         if timelock < config.min_timelock || timelock > config.max_timelock {
             return Err(ContractError::InvalidTimelock);
         }
+        let expires_at = env.ledger().timestamp() + timelock;
+        partial_fills::validate_fill_terms(&env, &fill_terms, amount, expires_at)?;
 
         // Generate swap ID
-        let swap_id = env.crypto().keccak256(&(
-            &initiator,
-            &token,
-            &amount,
-            &secret_hash,
-            &timelock,
-            &env.ledger().timestamp(),
-        ));
+        let swap_id = env.crypto().keccak256(
+            &(
+                initiator.clone(),
+                token.clone(),
+                amount,
+                secret_hash.clone(),
+                timelock,
+                env.ledger().timestamp(),
+            )
+                .to_xdr(&env),
+        );
 
         // Check if swap already exists
         if env.storage().persistent().has(&StorageKey::Swap(swap_id.clone())) {
             return Err(ContractError::SwapAlreadyExists);
         }
 
-        // Transfer tokens to contract
+        // Transfer tokens to contract, with the maker fee that funds fill rewards
+        let fee = htlc::calculate_fill_reward(amount, config.resolver_reward_rate);
         let token_client = token::Client::new(&env, &token);
-        token_client.transfer(&initiator, &env.current_contract_address(), &amount);
+        token_client.transfer(&initiator, &env.current_contract_address(), &(amount + fee));
 
         // Create swap
         let swap = Swap {
             initiator: initiator.clone(),
-            token,
+            token: token.clone(),
             amount,
             filled: 0,
             secret_hash,
-            timelock: env.ledger().timestamp() + timelock,
+            timelock: expires_at,
             ethereum_address,
             state: SwapState::Initiated,
-            partial_fill_enabled,
+            fill_mode: fill_terms.fill_mode,
             merkle_root,
+            merkle_version: partial_fills::MERKLE_TREE_VERSION,
             created_at: env.ledger().timestamp(),
+            secret_parts: fill_terms.secret_parts,
+            last_secret_index: 0,
+            released: 0,
+            fee,
+            min_fill_amount: fill_terms.min_fill_amount,
+            fill_step: fill_terms.fill_step,
+            exclusive_resolvers: fill_terms.exclusive_resolvers,
+            exclusivity_deadline: fill_terms.exclusivity_deadline,
         };
 
         // Store swap
         env.storage().persistent().set(&StorageKey::Swap(swap_id.clone()), &swap);
 
         // Update analytics
-        let mut analytics: Analytics = env.storage().instance().get(&StorageKey::Analytics).unwrap();
-        analytics.total_swaps += 1;
-        analytics.total_volume += amount;
-        env.storage().instance().set(&StorageKey::Analytics, &analytics);
+        analytics::record_swap(&env, &token, amount)?;
 
         let total_swaps: u32 = env.storage().instance().get(&StorageKey::TotalSwaps).unwrap();
         env.storage().instance().set(&StorageKey::TotalSwaps, &(total_swaps + 1));
@@ -184,9 +263,12 @@ impl StellarBridge {
     pub fn complete_swap(
         env: Env,
         swap_id: BytesN<32>,
+        resolver: Address,
         secret: BytesN<32>,
     ) -> Result<(), ContractError> {
         Self::require_not_paused(&env)?;
+        let resolver = Self::authorize_resolver(&resolver);
+        Self::require_active_resolver(&env, &resolver)?;
         
         let mut swap: Swap = env
             .storage()
@@ -196,6 +278,12 @@ impl StellarBridge {
 
         // Validate swap can be completed
         can_complete_swap(&env, &swap)?;
+        htlc::check_exclusivity(&env, &swap, &resolver)?;
+
+        // Multi-secret swaps only settle through withdraw_fill
+        if swap.secret_parts > 0 {
+            return Err(ContractError::InvalidSwapState);
+        }
 
         // Validate secret
         if !validate_secret(&env, &secret, &swap.secret_hash) {
@@ -210,20 +298,49 @@ impl StellarBridge {
         // Mark secret as used
         env.storage().persistent().set(&StorageKey::UsedSecret(secret.clone()), &true);
 
-        // Complete the swap
+        // Complete the swap; the completing resolver takes what is left of the fee
+        let reward = swap.fee;
         swap.state = SwapState::Completed;
+        swap.released = swap.amount;
+        swap.fee = 0;
         env.storage().persistent().set(&StorageKey::Swap(swap_id.clone()), &swap);
+        reservations::release_all(&env, &swap_id, &swap);
+        analytics::record_completion(&env, &swap)?;
+
+        let token_client = token::Client::new(&env, &swap.token);
+
+        // Credit escrowed partial fills and their rewards to the resolvers that
+        // filled them; they're pulled with claim_rewards, so one frozen
+        // recipient can't hold up the completion
+        let (payouts, rewards) = partial_fills::take_pending_payouts(&env, &swap_id);
+        for (fill_resolver, payout) in payouts.iter() {
+            rewards::accrue(&env, &fill_resolver, &swap.token, payout);
+            let reward = rewards.get(fill_resolver.clone()).unwrap_or(0);
+            let data = resolver::get_resolver(&env, &fill_resolver)?;
+            delegation::distribute_reward(&env, &fill_resolver, &data, &swap.token, reward);
+            analytics::record_fees(&env, &swap.token, reward)?;
+            reputation::record(&env, &fill_resolver, Outcome::FillCompleted);
+        }
 
         // Transfer remaining tokens to resolver
         let remaining_amount = swap.amount - swap.filled;
         if remaining_amount > 0 {
-            let token_client = token::Client::new(&env, &swap.token);
             token_client.transfer(
                 &env.current_contract_address(),
-                &env.invoker(),
+                &resolver,
                 &remaining_amount,
             );
         }
+        let mut resolver_data = resolver::get_resolver(&env, &resolver)?;
+        delegation::distribute_reward(&env, &resolver, &resolver_data, &swap.token, reward);
+        analytics::record_fees(&env, &swap.token, reward)?;
+
+        // Taking the remainder is a fill of its own for the completing resolver
+        if remaining_amount > 0 {
+            resolver_data.total_volume += remaining_amount;
+            reputation::apply(&env, &resolver, &mut resolver_data, Outcome::FillCompleted);
+            resolver::set_resolver(&env, &resolver, &resolver_data);
+        }
 
         env.events().publish(
             (Symbol::new(&env, "swap_completed"),),
@@ -241,24 +358,31 @@ impl StellarBridge {
             .get(&StorageKey::Swap(swap_id.clone()))
             .ok_or(ContractError::SwapNotFound)?;
 
-        let caller = env.invoker();
-        can_refund_swap(&env, &swap, &caller)?;
+        swap.initiator.require_auth();
+        can_refund_swap(&env, &swap, &swap.initiator)?;
 
-        // Update swap state
-        swap.state = SwapState::Refunded;
-        env.storage().persistent().set(&StorageKey::Swap(swap_id.clone()), &swap);
+        // Before the timelock only an immediate-or-cancel remainder can be
+        // refunded; the filled part stays escrowed for settlement
+        if !is_timelock_expired(&env, swap.timelock) {
+            let refund_amount = swap.amount - swap.filled + swap.fee;
+            swap.amount = swap.filled;
+            swap.fee = 0;
+            env.storage().persistent().set(&StorageKey::Swap(swap_id.clone()), &swap);
+            reservations::release_all(&env, &swap_id, &swap);
 
-        // Refund remaining tokens
-        let refund_amount = swap.amount - swap.filled;
-        if refund_amount > 0 {
             let token_client = token::Client::new(&env, &swap.token);
-            token_client.transfer(
-                &env.current_contract_address(),
-                &swap.initiator,
-                &refund_amount,
+            token_client.transfer(&env.current_contract_address(), &swap.initiator, &refund_amount);
+
+            env.events().publish(
+                (Symbol::new(&env, "swap_remainder_cancelled"),),
+                (swap_id, swap.initiator.clone(), refund_amount),
             );
+            return Ok(());
         }
 
+        let refund_amount = Self::return_escrow(&env, &swap_id, &mut swap, SwapState::Refunded);
+        analytics::record_refund(&env, &swap.token)?;
+
         env.events().publish(
             (Symbol::new(&env, "swap_refunded"),),
             (swap_id, swap.initiator.clone(), refund_amount),
@@ -267,79 +391,246 @@ impl StellarBridge {
         Ok(())
     }
 
+    // Close out a lapsed swap on the initiator's behalf; callable by anyone
+    // once the timelock has passed
+    pub fn expire_swap(env: Env, swap_id: BytesN<32>) -> Result<(), ContractError> {
+        let mut swap: Swap = env
+            .storage()
+            .persistent()
+            .get(&StorageKey::Swap(swap_id.clone()))
+            .ok_or(ContractError::SwapNotFound)?;
+
+        match swap.state {
+            SwapState::Initiated | SwapState::PartialFilled => {}
+            _ => return Err(ContractError::InvalidSwapState),
+        }
+        if !is_timelock_expired(&env, swap.timelock) {
+            return Err(ContractError::TimelockNotExpired);
+        }
+
+        let refund_amount = Self::return_escrow(&env, &swap_id, &mut swap, SwapState::Expired);
+        analytics::record_expiry(&env, &swap.token)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "swap_expired"),),
+            (swap_id, swap.initiator.clone(), refund_amount),
+        );
+
+        Ok(())
+    }
+
     // Execute partial fill
     pub fn execute_partial_fill(
         env: Env,
         swap_id: BytesN<32>,
+        resolver: Address,
         fill_amount: i128,
         merkle_proof: Vec<BytesN<32>>,
+        nonce: u64,
+    ) -> Result<(), ContractError> {
+        // Auctioned swaps are only filled through place_bid
+        if auction::get_auction(&env, &swap_id).is_some() {
+            return Err(ContractError::InvalidSwapState);
+        }
+
+        let resolver = Self::authorize_resolver(&resolver);
+        Self::fill_swap(&env, &swap_id, &resolver, fill_amount, merkle_proof, nonce, 0)
+    }
+
+    // Reserve a slice of the swap ahead of filling it. `amount` and `nonce` name
+    // the committed fill the resolver will reveal through execute_partial_fill or
+    // place_bid, proven against the swap's merkle root.
+    pub fn reserve_fill(
+        env: Env,
+        swap_id: BytesN<32>,
+        resolver: Address,
+        amount: i128,
+        nonce: u64,
+        merkle_proof: Vec<BytesN<32>>,
     ) -> Result<(), ContractError> {
         Self::require_not_paused(&env)?;
-        Self::require_active_resolver(&env)?;
+        let resolver = Self::authorize_resolver(&resolver);
+        Self::require_active_resolver(&env, &resolver)?;
 
-        let mut swap: Swap = env
+        let swap: Swap = env
             .storage()
             .persistent()
             .get(&StorageKey::Swap(swap_id.clone()))
             .ok_or(ContractError::SwapNotFound)?;
+        let fill_count = partial_fills::get_fill_count(&env, &swap_id);
+        htlc::validate_partial_fill(&env, &swap, fill_count, amount, &resolver)?;
+
+        let reservation = reservations::reserve(
+            &env,
+            &swap_id,
+            &swap,
+            &resolver,
+            amount,
+            nonce,
+            merkle_proof,
+        )?;
 
-        let resolver = env.invoker();
+        env.events().publish(
+            (Symbol::new(&env, "fill_reserved"),),
+            (swap_id, resolver, amount, reservation.expires_at),
+        );
 
-        // Validate partial fill
-        htlc::validate_partial_fill(&env, &swap, fill_amount, &resolver)?;
+        Ok(())
+    }
 
-        // Verify merkle proof (simplified for demo)
-        // In production, implement proper merkle proof verification
+    // Slash lapsed reservations; callable by anyone
+    pub fn expire_reservations(env: Env, swap_id: BytesN<32>) -> Result<i128, ContractError> {
+        let swap: Swap = env
+            .storage()
+            .persistent()
+            .get(&StorageKey::Swap(swap_id.clone()))
+            .ok_or(ContractError::SwapNotFound)?;
 
-        // Update swap
-        swap.filled += fill_amount;
-        if swap.filled == swap.amount {
-            swap.state = SwapState::Completed;
-        } else {
-            swap.state = SwapState::PartialFilled;
-        }
-        env.storage().persistent().set(&StorageKey::Swap(swap_id.clone()), &swap);
+        Ok(reservations::expire(&env, &swap_id, &swap))
+    }
 
-        // Calculate and transfer rewards
-        let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
-        let reward = htlc::calculate_fill_reward(fill_amount, config.resolver_reward_rate);
-        
-        if reward > 0 {
-            let token_client = token::Client::new(&env, &swap.token);
-            token_client.transfer(&env.current_contract_address(), &resolver, &reward);
+    // Start a Dutch auction pricing the swap's fills
+    pub fn start_auction(
+        env: Env,
+        swap_id: BytesN<32>,
+        start_rate: i128,
+        end_rate: i128,
+        duration: u64,
+        points: Vec<AuctionPoint>,
+        gas_bump: Option<GasBump>,
+    ) -> Result<(), ContractError> {
+        Self::require_not_paused(&env)?;
+
+        let swap: Swap = env
+            .storage()
+            .persistent()
+            .get(&StorageKey::Swap(swap_id.clone()))
+            .ok_or(ContractError::SwapNotFound)?;
+        swap.initiator.require_auth();
+
+        auction::start_auction(
+            &env,
+            &swap_id,
+            &swap,
+            start_rate,
+            end_rate,
+            duration,
+            points,
+            gas_bump,
+        )?;
+
+        env.events().publish(
+            (Symbol::new(&env, "auction_started"),),
+            (swap_id, start_rate, end_rate, duration),
+        );
+
+        Ok(())
+    }
+
+    // Fill at the current auction rate if it does not exceed `bid_rate`
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_bid(
+        env: Env,
+        swap_id: BytesN<32>,
+        resolver: Address,
+        bid_rate: i128,
+        fill_amount: i128,
+        merkle_proof: Vec<BytesN<32>>,
+        nonce: u64,
+    ) -> Result<i128, ContractError> {
+        let auction = auction::get_auction(&env, &swap_id).ok_or(ContractError::AuctionNotFound)?;
+        let rate = auction::current_rate(&env, &auction);
+        if bid_rate < rate {
+            return Err(ContractError::BidBelowCurrentRate);
         }
 
-        // Update resolver stats
-        let mut resolver_data: Resolver = env
+        let resolver = Self::authorize_resolver(&resolver);
+        Self::fill_swap(&env, &swap_id, &resolver, fill_amount, merkle_proof, nonce, rate)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "bid_accepted"),),
+            (swap_id, resolver, fill_amount, rate),
+        );
+
+        Ok(rate)
+    }
+
+    // Withdraw one fill segment of a multi-secret swap by revealing its secret
+    #[allow(clippy::too_many_arguments)]
+    pub fn withdraw_fill(
+        env: Env,
+        swap_id: BytesN<32>,
+        resolver: Address,
+        fill_amount: i128,
+        secret: BytesN<32>,
+        secret_index: u32,
+        merkle_proof: Vec<BytesN<32>>,
+    ) -> Result<(), ContractError> {
+        Self::require_not_paused(&env)?;
+        let resolver = Self::authorize_resolver(&resolver);
+        Self::require_active_resolver(&env, &resolver)?;
+
+        let mut swap: Swap = env
             .storage()
             .persistent()
-            .get(&StorageKey::Resolver(resolver.clone()))
-            .unwrap_or(Resolver {
-                stake: 0,
-                reputation: 1000,
-                total_volume: 0,
-                success_rate: 10000,
-                active: false,
-                registration_time: env.ledger().timestamp(),
-            });
+            .get(&StorageKey::Swap(swap_id.clone()))
+            .ok_or(ContractError::SwapNotFound)?;
 
+        let reward = partial_fills::withdraw_fill_segment(
+            &env,
+            &swap_id,
+            &mut swap,
+            &resolver,
+            fill_amount,
+            &secret,
+            secret_index,
+            merkle_proof,
+        )?;
+        env.storage().persistent().set(&StorageKey::Swap(swap_id.clone()), &swap);
+        analytics::record_fill(&env, &swap.token)?;
+        if swap.state == SwapState::Completed {
+            analytics::record_completion(&env, &swap)?;
+        }
+
+        // Release the segment to the resolver
+        let token_client = token::Client::new(&env, &swap.token);
+        token_client.transfer(&env.current_contract_address(), &resolver, &fill_amount);
+
+        let mut resolver_data = resolver::get_resolver(&env, &resolver)?;
+        delegation::distribute_reward(&env, &resolver, &resolver_data, &swap.token, reward);
+        analytics::record_fees(&env, &swap.token, reward)?;
         resolver_data.total_volume += fill_amount;
-        env.storage().persistent().set(&StorageKey::Resolver(resolver.clone()), &resolver_data);
+        reputation::apply(&env, &resolver, &mut resolver_data, Outcome::FillCompleted);
+        resolver::set_resolver(&env, &resolver, &resolver_data);
 
         env.events().publish(
-            (Symbol::new(&env, "partial_fill_executed"),),
-            (swap_id, resolver, fill_amount, swap.filled),
+            (Symbol::new(&env, "fill_withdrawn"),),
+            (swap_id, resolver, fill_amount, secret_index, secret),
         );
 
         Ok(())
     }
 
     // Register as resolver
-    pub fn register_resolver(env: Env, stake_amount: i128) -> Result<(), ContractError> {
+    pub fn register_resolver(
+        env: Env,
+        resolver: Address,
+        stake_amount: i128,
+    ) -> Result<(), ContractError> {
+        resolver.require_auth();
+
         let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
-        let resolver = env.invoker();
 
-        if stake_amount < config.min_stake {
+        resolver::check_can_register(&env, &resolver, config.whitelist_mode)?;
+        if resolver::owner_of(&env, &resolver).is_some() {
+            return Err(ContractError::InvalidAddress);
+        }
+
+        // Own stake below min_stake registers the resolver inactive until
+        // delegations make up the difference, but it must still cover the
+        // self-stake share so registrations can't flood the index
+        let min_self_stake = config.min_stake * delegation::MIN_SELF_STAKE_RATE / 10000;
+        if stake_amount <= 0 || stake_amount < min_self_stake {
             return Err(ContractError::InsufficientStake);
         }
 
@@ -352,16 +643,29 @@ impl StellarBridge {
         token_client.transfer(&resolver, &env.current_contract_address(), &stake_amount);
 
         // Register resolver
-        let resolver_data = Resolver {
+        let mut resolver_data = Resolver {
             stake: stake_amount,
-            reputation: 1000, // Starting reputation
+            reputation: reputation::BASE_REPUTATION,
             total_volume: 0,
             success_rate: 10000, // 100% starting rate
             active: true,
             registration_time: env.ledger().timestamp(),
+            unbonding_amount: 0,
+            unbonding_until: 0,
+            last_outcome_at: env.ledger().timestamp(),
+            delegated_stake: 0,
+            recent_failures: 0,
+            failure_window_start: 0,
+            jailed_until: 0,
+            jail_reason: Symbol::new(&env, ""),
         };
+        resolver::sync_active(&mut resolver_data, config.min_stake);
 
-        env.storage().persistent().set(&StorageKey::Resolver(resolver.clone()), &resolver_data);
+        resolver::set_resolver(&env, &resolver, &resolver_data);
+
+        let mut analytics = analytics::get(&env);
+        analytics.total_resolvers = resolver::add_to_index(&env, &resolver);
+        analytics::set(&env, &analytics);
 
         env.events().publish(
             (Symbol::new(&env, "resolver_registered"),),
@@ -371,6 +675,239 @@ impl StellarBridge {
         Ok(())
     }
 
+    pub fn set_resolver_profile(
+        env: Env,
+        resolver: Address,
+        profile: ResolverProfile,
+    ) -> Result<(), ContractError> {
+        resolver.require_auth();
+        let mut profile = profile;
+        resolver::set_profile(&env, &resolver, &mut profile)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "resolver_profile_updated"),),
+            (resolver, profile.display_name),
+        );
+
+        Ok(())
+    }
+
+    // Stake native_token behind a resolver
+    pub fn delegate(
+        env: Env,
+        delegator: Address,
+        resolver: Address,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        delegator.require_auth();
+        delegation::delegate(&env, &delegator, &resolver, amount)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "stake_delegated"),),
+            (delegator, resolver, amount),
+        );
+
+        Ok(())
+    }
+
+    // Start unbonding delegated stake; withdrawable after the delegator unbonding period
+    pub fn undelegate(
+        env: Env,
+        delegator: Address,
+        resolver: Address,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        delegator.require_auth();
+        let delegation = delegation::undelegate(&env, &delegator, &resolver, amount)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "stake_undelegated"),),
+            (delegator, resolver, amount, delegation.unbonding_until),
+        );
+
+        Ok(())
+    }
+
+    pub fn withdraw_delegation(
+        env: Env,
+        delegator: Address,
+        resolver: Address,
+    ) -> Result<i128, ContractError> {
+        delegator.require_auth();
+        let amount = delegation::withdraw(&env, &delegator, &resolver)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "delegation_withdrawn"),),
+            (delegator, resolver, amount),
+        );
+
+        Ok(amount)
+    }
+
+    // Share of the delegators' rewards the resolver keeps, in basis points
+    pub fn set_commission_rate(
+        env: Env,
+        resolver: Address,
+        commission_rate: u32,
+    ) -> Result<(), ContractError> {
+        resolver.require_auth();
+        let pool = delegation::set_commission_rate(&env, &resolver, commission_rate)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "commission_rate_set"), resolver),
+            (commission_rate, pool.commission_effective_at),
+        );
+
+        Ok(())
+    }
+
+    // Settle a delegation's rewards and claim everything accrued in `token`
+    pub fn claim_delegation_rewards(
+        env: Env,
+        delegator: Address,
+        resolver: Address,
+        token: Address,
+    ) -> Result<i128, ContractError> {
+        delegator.require_auth();
+        delegation::settle_rewards(&env, &delegator, &resolver);
+        let amount = rewards::claim(&env, &delegator, &token)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "rewards_claimed"),),
+            (delegator, token, amount),
+        );
+
+        Ok(amount)
+    }
+
+    // Pay out the settled fill slices and rewards accrued in `token`
+    pub fn claim_rewards(env: Env, resolver: Address, token: Address) -> Result<i128, ContractError> {
+        resolver.require_auth();
+        let amount = rewards::claim(&env, &resolver, &token)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "rewards_claimed"),),
+            (resolver, token, amount),
+        );
+
+        Ok(amount)
+    }
+
+    // Let a hot wallet fill, complete and reserve on the resolver's behalf
+    pub fn add_operator(env: Env, resolver: Address, operator: Address) -> Result<(), ContractError> {
+        resolver.require_auth();
+        resolver::add_operator(&env, &resolver, &operator)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "operator_added"),),
+            (resolver, operator),
+        );
+
+        Ok(())
+    }
+
+    pub fn revoke_operator(env: Env, resolver: Address, operator: Address) -> Result<(), ContractError> {
+        resolver.require_auth();
+        resolver::remove_operator(&env, &resolver, &operator)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "operator_revoked"),),
+            (resolver, operator),
+        );
+
+        Ok(())
+    }
+
+    // Swap an operator key for a new one in a single call
+    pub fn rotate_operator(
+        env: Env,
+        resolver: Address,
+        old_operator: Address,
+        new_operator: Address,
+    ) -> Result<(), ContractError> {
+        resolver.require_auth();
+        resolver::remove_operator(&env, &resolver, &old_operator)?;
+        resolver::add_operator(&env, &resolver, &new_operator)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "operator_rotated"),),
+            (resolver, old_operator, new_operator),
+        );
+
+        Ok(())
+    }
+
+    // Return a jailed resolver to rotation once its cooldown has passed
+    pub fn unjail(env: Env, resolver: Address) -> Result<(), ContractError> {
+        resolver.require_auth();
+        let resolver_data = jailing::unjail(&env, &resolver)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "resolver_unjailed"),),
+            (resolver, resolver_data.active),
+        );
+
+        Ok(())
+    }
+
+    pub fn increase_stake(env: Env, resolver: Address, amount: i128) -> Result<(), ContractError> {
+        resolver.require_auth();
+        let resolver_data = resolver::increase_stake(&env, &resolver, amount)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "stake_increased"),),
+            (resolver, amount, resolver_data.stake),
+        );
+
+        Ok(())
+    }
+
+    // Start unbonding part of the stake; the resolver can't fill until it is withdrawn
+    pub fn request_unstake(env: Env, resolver: Address, amount: i128) -> Result<(), ContractError> {
+        resolver.require_auth();
+        let resolver_data = resolver::request_unstake(&env, &resolver, amount)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "unstake_requested"),),
+            (resolver, amount, resolver_data.unbonding_until),
+        );
+
+        Ok(())
+    }
+
+    pub fn withdraw_stake(env: Env, resolver: Address) -> Result<i128, ContractError> {
+        resolver.require_auth();
+        let amount = resolver::withdraw_stake(&env, &resolver)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "stake_withdrawn"),),
+            (resolver, amount),
+        );
+
+        Ok(amount)
+    }
+
+    // Slash a resolver that failed to deliver on a swap; callable by the admin
+    // or an arbiter
+    pub fn slash_resolver(
+        env: Env,
+        caller: Address,
+        resolver: Address,
+        swap_id: BytesN<32>,
+        amount: i128,
+        reason: Symbol,
+    ) -> Result<(), ContractError> {
+        caller.require_auth();
+
+        let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        if caller != config.admin && !slashing::is_arbiter(&env, &caller) {
+            return Err(ContractError::Unauthorized);
+        }
+
+        slashing::slash_resolver(&env, &resolver, &swap_id, amount, reason, &caller)?;
+        Ok(())
+    }
+
     // View functions
     pub fn get_swap(env: Env, swap_id: BytesN<32>) -> Result<Swap, ContractError> {
         env.storage()
@@ -379,30 +916,259 @@ impl StellarBridge {
             .ok_or(ContractError::SwapNotFound)
     }
 
+    // Reputation is reported with decay applied up to the current ledger time
     pub fn get_resolver(env: Env, resolver: Address) -> Result<Resolver, ContractError> {
-        env.storage()
+        let mut data = resolver::get_resolver(&env, &resolver)?;
+        data.reputation = reputation::current(&env, &data);
+        Ok(data)
+    }
+
+    // Paginated fill history, oldest first; `limit` is capped at MAX_FILLS_PAGE
+    pub fn get_partial_fills(
+        env: Env,
+        swap_id: BytesN<32>,
+        start: u32,
+        limit: u32,
+    ) -> Vec<PartialFill> {
+        partial_fills::get_partial_fills(&env, &swap_id, start, limit)
+    }
+
+    pub fn get_fill_count(env: Env, swap_id: BytesN<32>) -> u32 {
+        partial_fills::get_fill_count(&env, &swap_id)
+    }
+
+    // Escrowed fill amounts owed to each resolver once the secret is revealed
+    pub fn get_pending_payouts(env: Env, swap_id: BytesN<32>) -> Map<Address, i128> {
+        partial_fills::get_pending_payouts(&env, &swap_id)
+    }
+
+    pub fn get_auction(env: Env, swap_id: BytesN<32>) -> Result<Auction, ContractError> {
+        auction::get_auction(&env, &swap_id).ok_or(ContractError::AuctionNotFound)
+    }
+
+    pub fn get_current_rate(env: Env, swap_id: BytesN<32>) -> Result<i128, ContractError> {
+        let auction = auction::get_auction(&env, &swap_id).ok_or(ContractError::AuctionNotFound)?;
+        Ok(auction::current_rate(&env, &auction))
+    }
+
+    // Rate, destination amount and reward a fill of `amount` would get right now
+    pub fn quote_fill(env: Env, swap_id: BytesN<32>, amount: i128) -> Result<FillQuote, ContractError> {
+        let swap: Swap = env
+            .storage()
+            .persistent()
+            .get(&StorageKey::Swap(swap_id.clone()))
+            .ok_or(ContractError::SwapNotFound)?;
+        if amount <= 0 {
+            return Err(ContractError::InvalidFillAmount);
+        }
+        if swap.filled + amount > swap.amount {
+            return Err(ContractError::ExceedsSwapAmount);
+        }
+
+        let auction = auction::get_auction(&env, &swap_id).ok_or(ContractError::AuctionNotFound)?;
+        let rate = auction::current_rate(&env, &auction);
+        let mut filled = swap.clone();
+        filled.filled += amount;
+
+        Ok(FillQuote {
+            rate,
+            taking_amount: auction::taking_amount(amount, rate),
+            reward: partial_fills::take_reward(&env, &mut filled, amount),
+        })
+    }
+
+    pub fn get_reservations(env: Env, swap_id: BytesN<32>) -> Result<SwapReservations, ContractError> {
+        let swap: Swap = env
+            .storage()
+            .persistent()
+            .get(&StorageKey::Swap(swap_id.clone()))
+            .ok_or(ContractError::SwapNotFound)?;
+
+        Ok(reservations::view(&env, &swap_id, &swap))
+    }
+
+    // Suggested split of the swap's unreserved remainder across `resolvers`, at
+    // most MAX_PLAN_RESOLVERS of them. Inactive or unknown resolvers get no slice.
+    pub fn plan_fills(
+        env: Env,
+        swap_id: BytesN<32>,
+        resolvers: Vec<Address>,
+        weighting: FillWeighting,
+    ) -> Result<Vec<FillSlice>, ContractError> {
+        if resolvers.len() > partial_fills::MAX_PLAN_RESOLVERS {
+            return Err(ContractError::LimitExceeded);
+        }
+        let swap: Swap = env
+            .storage()
             .persistent()
-            .get(&StorageKey::Resolver(resolver))
-            .ok_or(ContractError::ResolverNotFound)
+            .get(&StorageKey::Swap(swap_id.clone()))
+            .ok_or(ContractError::SwapNotFound)?;
+        match swap.state {
+            SwapState::Initiated | SwapState::PartialFilled => {}
+            _ => return Err(ContractError::InvalidSwapState),
+        }
+
+        let mut weights = Vec::new(&env);
+        let mut caps = Vec::new(&env);
+        for (i, resolver) in resolvers.iter().enumerate() {
+            let data = resolver::get_resolver(&env, &resolver).ok();
+            let data = match data {
+                Some(data)
+                    if resolver::check_can_fill(&data).is_ok()
+                        && resolver::check_access(&env, &resolver).is_ok()
+                        && htlc::check_exclusivity(&env, &swap, &resolver).is_ok() =>
+                {
+                    data
+                }
+                _ => {
+                    weights.push_back(0);
+                    caps.push_back(0);
+                    continue;
+                }
+            };
+            let (weight, cap) = match &weighting {
+                FillWeighting::Stake => (delegation::effective_stake(&data), i128::MAX),
+                FillWeighting::Reputation => {
+                    (reputation::current(&env, &data) as i128, i128::MAX)
+                }
+                FillWeighting::Capacity(capacities) => {
+                    let capacity = capacities.get(i as u32).unwrap_or(0).max(0);
+                    (capacity, capacity)
+                }
+            };
+            weights.push_back(weight);
+            caps.push_back(cap);
+        }
+
+        // Respect the swap's fill mode
+        let fill_count = partial_fills::get_fill_count(&env, &swap_id);
+        let (max_slices, min_fill) = match swap.fill_mode {
+            FillMode::FillOrKill => (1, swap.amount),
+            FillMode::AllowMultipleFills(max_fills) => {
+                (max_fills.saturating_sub(fill_count), swap.min_fill_amount)
+            }
+            FillMode::AllowPartial | FillMode::ImmediateOrCancel => {
+                (resolvers.len(), swap.min_fill_amount)
+            }
+        };
+
+        // Reserved amounts aren't plannable, so slices may not clear the remainder
+        let remaining = reservations::view(&env, &swap_id, &swap).available;
+        let sizes = partial_fills::calculate_optimal_fill_sizes(
+            &env,
+            remaining,
+            remaining == swap.amount - swap.filled,
+            &weights,
+            &caps,
+            max_slices,
+            min_fill,
+            swap.fill_step,
+        );
+
+        let mut plan = Vec::new(&env);
+        for (resolver, amount) in resolvers.iter().zip(sizes.iter()) {
+            if amount > 0 {
+                plan.push_back(FillSlice { resolver, amount });
+            }
+        }
+        Ok(plan)
+    }
+
+    pub fn get_pending_rewards(env: Env, resolver: Address, token: Address) -> i128 {
+        rewards::get_rewards(&env, &resolver, &token)
+    }
+
+    pub fn get_delegation(env: Env, delegator: Address, resolver: Address) -> Option<DelegationInfo> {
+        delegation::info(&env, &delegator, &resolver)
+    }
+
+    pub fn get_delegation_pool(env: Env, resolver: Address) -> DelegationPool {
+        delegation::get_pool(&env, &resolver)
+    }
+
+    // Delegator rewards from `resolver` not yet settled into get_pending_rewards
+    pub fn get_delegation_rewards(
+        env: Env,
+        delegator: Address,
+        resolver: Address,
+        token: Address,
+    ) -> i128 {
+        let pool = delegation::get_pool(&env, &resolver);
+        delegation::get_delegation(&env, &delegator, &resolver)
+            .map_or(0, |entry| delegation::unsettled_rewards(&pool, &entry, &token))
+    }
+
+    pub fn get_operators(env: Env, resolver: Address) -> Vec<Address> {
+        resolver::get_operators(&env, &resolver)
+    }
+
+    pub fn get_resolver_profile(env: Env, resolver: Address) -> Option<ResolverProfile> {
+        resolver::get_profile(&env, &resolver)
+    }
+
+    // Active resolvers ranked by `sort_by`, mirroring ResolverRegistry.getTopResolvers.
+    // Ranks one page of the registration index (`limit` capped at
+    // MAX_RESOLVERS_PAGE) and returns its best `count`. A full leaderboard walks
+    // `start` from 0 to total_resolvers in steps of MAX_RESOLVERS_PAGE and keeps
+    // the best `count` across pages, comparing the same key via get_resolver.
+    pub fn get_top_resolvers(
+        env: Env,
+        start: u32,
+        limit: u32,
+        count: u32,
+        sort_by: ResolverSort,
+    ) -> Vec<Address> {
+        resolver::top_resolvers(&env, start, limit, count, &sort_by)
+    }
+
+    pub fn get_active_resolver_count(env: Env) -> u32 {
+        resolver::get_active_count(&env)
+    }
+
+    pub fn get_slash_history(env: Env, resolver: Address) -> Vec<SlashRecord> {
+        slashing::get_history(&env, &resolver)
+    }
+
+    pub fn get_insurance_fund(env: Env) -> i128 {
+        slashing::get_insurance_fund(&env)
+    }
+
+    pub fn get_network_fee(env: Env) -> Option<NetworkFee> {
+        auction::get_network_fee(&env)
     }
 
     pub fn get_analytics(env: Env) -> Analytics {
-        env.storage()
-            .instance()
-            .get(&StorageKey::Analytics)
-            .unwrap_or(Analytics {
-                total_volume: 0,
-                total_swaps: 0,
-                total_resolvers: 0,
-                success_rate: 10000,
-                average_completion_time: 0,
-            })
+        analytics::get(&env)
+    }
+
+    pub fn get_token_analytics(env: Env, token: Address) -> TokenAnalytics {
+        analytics::get_token(&env, &token)
+    }
+
+    // Daily buckets for `token` from `from_day` through `to_day`, one per day
+    pub fn get_daily_analytics(
+        env: Env,
+        token: Address,
+        from_day: u64,
+        to_day: u64,
+    ) -> Result<Vec<TokenAnalytics>, ContractError> {
+        analytics::get_buckets(&env, &token, from_day, to_day)
     }
 
     pub fn is_secret_used(env: Env, secret: BytesN<32>) -> bool {
         env.storage().persistent().has(&StorageKey::UsedSecret(secret))
     }
 
+    pub fn is_fill_nonce_used(env: Env, swap_id: BytesN<32>, resolver: Address, nonce: u64) -> bool {
+        env.storage()
+            .persistent()
+            .has(&StorageKey::FillNonce(swap_id, resolver, nonce))
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&StorageKey::Paused).unwrap_or(false)
+    }
+
     // Admin functions
     pub fn pause(env: Env) -> Result<(), ContractError> {
         Self::require_admin(&env)?;
@@ -416,12 +1182,284 @@ impl StellarBridge {
         Ok(())
     }
 
+    pub fn set_reservation_config(
+        env: Env,
+        reservation_period: u64,
+        reservation_bond_rate: u32,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+
+        if reservation_period == 0
+            || reservation_period > reservations::MAX_RESERVATION_PERIOD
+            || reservation_bond_rate > 10000
+        {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        let mut config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        config.reservation_period = reservation_period;
+        config.reservation_bond_rate = reservation_bond_rate;
+        env.storage().instance().set(&StorageKey::Config, &config);
+        Ok(())
+    }
+
+    pub fn set_unbonding_period(env: Env, unbonding_period: u64) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        let mut config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        config.unbonding_period = unbonding_period;
+        env.storage().instance().set(&StorageKey::Config, &config);
+        Ok(())
+    }
+
+    pub fn set_min_reputation(env: Env, min_reputation: u32) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        if min_reputation > reputation::MAX_REPUTATION {
+            return Err(ContractError::InvalidAmount);
+        }
+        let mut config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        config.min_reputation = min_reputation;
+        env.storage().instance().set(&StorageKey::Config, &config);
+        Ok(())
+    }
+
+    // Existing resolvers keep filling when whitelist mode is switched on
+    pub fn set_whitelist_mode(env: Env, enabled: bool) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        let mut config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        config.whitelist_mode = enabled;
+        env.storage().instance().set(&StorageKey::Config, &config);
+        Ok(())
+    }
+
+    pub fn approve_resolver(env: Env, resolver: Address) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        resolver::set_approval(&env, &resolver, true);
+        env.events().publish((Symbol::new(&env, "resolver_approved"),), resolver);
+        Ok(())
+    }
+
+    // Revoked addresses can't register, and registered ones stop filling
+    pub fn revoke_resolver(env: Env, resolver: Address) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        resolver::set_approval(&env, &resolver, false);
+        env.events().publish((Symbol::new(&env, "resolver_revoked"),), resolver);
+        Ok(())
+    }
+
+    // Temporarily bar a resolver from filling without touching its approval
+    pub fn suspend_resolver(env: Env, resolver: Address, suspended: bool) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        resolver::set_suspended(&env, &resolver, suspended);
+        env.events().publish(
+            (Symbol::new(&env, "resolver_suspended"),),
+            (resolver, suspended),
+        );
+        Ok(())
+    }
+
+    pub fn set_arbiter(env: Env, arbiter: Address, enabled: bool) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        if enabled {
+            env.storage().instance().set(&StorageKey::Arbiter(arbiter), &true);
+        } else {
+            env.storage().instance().remove(&StorageKey::Arbiter(arbiter));
+        }
+        Ok(())
+    }
+
+    pub fn set_insurance_rate(env: Env, insurance_rate: u32) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        if insurance_rate > 10000 {
+            return Err(ContractError::InvalidAmount);
+        }
+        let mut config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        config.insurance_rate = insurance_rate;
+        env.storage().instance().set(&StorageKey::Config, &config);
+        Ok(())
+    }
+
+    // Pay out of the insurance fund to compensate an affected user
+    pub fn withdraw_insurance(env: Env, to: Address, amount: i128) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        slashing::withdraw_insurance(&env, &to, amount)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "insurance_withdrawn"),),
+            (to, amount),
+        );
+
+        Ok(())
+    }
+
+    pub fn set_jail_policy(env: Env, threshold: u32, window: u64, cooldown: u64) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        let mut config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        config.jail_threshold = threshold;
+        config.jail_window = window;
+        config.jail_cooldown = cooldown;
+        env.storage().instance().set(&StorageKey::Config, &config);
+        Ok(())
+    }
+
+    pub fn set_delegator_unbonding_period(env: Env, unbonding_period: u64) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        let mut config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        config.delegator_unbonding_period = unbonding_period;
+        env.storage().instance().set(&StorageKey::Config, &config);
+        Ok(())
+    }
+
+    pub fn set_fee_oracle(env: Env, oracle: Option<Address>) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        match oracle {
+            Some(oracle) => env.storage().instance().set(&StorageKey::FeeOracle, &oracle),
+            None => env.storage().instance().remove(&StorageKey::FeeOracle),
+        }
+        env.storage().instance().remove(&StorageKey::NetworkFee);
+        Ok(())
+    }
+
+    // Oracle entrypoint for current network fee conditions
+    pub fn report_network_fee(env: Env, fee: i128) -> Result<(), ContractError> {
+        let oracle: Address = env
+            .storage()
+            .instance()
+            .get(&StorageKey::FeeOracle)
+            .ok_or(ContractError::Unauthorized)?;
+        oracle.require_auth();
+
+        if fee < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        let report = NetworkFee {
+            fee,
+            updated_at: env.ledger().timestamp(),
+        };
+        env.storage().instance().set(&StorageKey::NetworkFee, &report);
+
+        env.events().publish((Symbol::new(&env, "network_fee_reported"),), fee);
+
+        Ok(())
+    }
+
+    // Rewrites a swap stored with partial_fill_enabled into the fill mode layout
+    pub fn migrate_swap(env: Env, swap_id: BytesN<32>) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+
+        let key = StorageKey::Swap(swap_id);
+        let stored: Map<Symbol, Val> = env
+            .storage()
+            .persistent()
+            .get(&key)
+            .ok_or(ContractError::SwapNotFound)?;
+
+        // Decoding a mismatched layout traps, so check for the legacy field first
+        if !stored.contains_key(Symbol::new(&env, "partial_fill_enabled")) {
+            return Err(ContractError::InvalidSwapState);
+        }
+        let legacy = LegacySwap::try_from_val(&env, &stored.to_val())
+            .map_err(|_| ContractError::InvalidSwapState)?;
+
+        env.storage().persistent().set(&key, &Swap::from(legacy));
+        Ok(())
+    }
+
     // Internal helper functions
-    fn require_admin(env: &Env) -> Result<(), ContractError> {
-        let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
-        if env.invoker() != config.admin {
-            return Err(ContractError::Unauthorized);
+    #[allow(clippy::too_many_arguments)]
+    fn fill_swap(
+        env: &Env,
+        swap_id: &BytesN<32>,
+        resolver: &Address,
+        fill_amount: i128,
+        merkle_proof: Vec<BytesN<32>>,
+        nonce: u64,
+        rate: i128,
+    ) -> Result<(), ContractError> {
+        // `resolver` is the owner, already authorized by the caller
+        Self::require_not_paused(env)?;
+        Self::require_active_resolver(env, resolver)?;
+
+        let mut swap: Swap = env
+            .storage()
+            .persistent()
+            .get(&StorageKey::Swap(swap_id.clone()))
+            .ok_or(ContractError::SwapNotFound)?;
+
+        // Honour live reservations on the swap
+        let leaf = partial_fills::fill_leaf(env, resolver, fill_amount, nonce);
+        reservations::consume(env, swap_id, &swap, resolver, fill_amount, &leaf)?;
+
+        // Validate fill, verify merkle proof and consume nonce
+        partial_fills::execute_partial_fill(
+            env,
+            swap_id,
+            &mut swap,
+            resolver,
+            fill_amount,
+            merkle_proof,
+            nonce,
+            rate,
+        )?;
+        env.storage().persistent().set(&StorageKey::Swap(swap_id.clone()), &swap);
+
+        // Update resolver stats
+        let mut resolver_data = resolver::get_resolver(env, resolver)?;
+        resolver_data.total_volume += fill_amount;
+        resolver::set_resolver(env, resolver, &resolver_data);
+        analytics::record_fill(env, &swap.token)?;
+
+        env.events().publish(
+            (Symbol::new(env, "partial_fill_executed"),),
+            (swap_id.clone(), resolver.clone(), fill_amount, swap.filled),
+        );
+
+        Ok(())
+    }
+
+    // Return everything still escrowed to the initiator, including unsettled
+    // partial fills and the unspent maker fee, and close the swap in `state`
+    fn return_escrow(env: &Env, swap_id: &BytesN<32>, swap: &mut Swap, state: SwapState) -> i128 {
+        let (payouts, rewards) = partial_fills::take_pending_payouts(env, swap_id);
+        let mut refund_amount = swap.amount - swap.released + swap.fee;
+        for fill_resolver in payouts.keys().iter() {
+            refund_amount += rewards.get(fill_resolver.clone()).unwrap_or(0);
+            reputation::record(env, &fill_resolver, Outcome::RefundedAfterFill);
         }
+
+        swap.state = state;
+        swap.released = swap.amount;
+        swap.fee = 0;
+        env.storage().persistent().set(&StorageKey::Swap(swap_id.clone()), swap);
+        reservations::release_all(env, swap_id, swap);
+
+        if refund_amount > 0 {
+            let token_client = token::Client::new(env, &swap.token);
+            token_client.transfer(
+                &env.current_contract_address(),
+                &swap.initiator,
+                &refund_amount,
+            );
+        }
+
+        refund_amount
+    }
+
+    // Fill, complete and reservation entrypoints accept the resolver or one of
+    // its operators; everything is accounted to the owning resolver
+    fn authorize_resolver(caller: &Address) -> Address {
+        caller.require_auth();
+        let env = caller.env();
+        resolver::owner_of(env, caller).unwrap_or(caller.clone())
+    }
+
+    fn require_admin(env: &Env) -> Result<(), ContractError> {
+        let config: Config = env
+            .storage()
+            .instance()
+            .get(&StorageKey::Config)
+            .ok_or(ContractError::Unauthorized)?;
+        config.admin.require_auth();
         Ok(())
     }
 
@@ -433,18 +1471,15 @@ impl StellarBridge {
         Ok(())
     }
 
-    fn require_active_resolver(env: &Env) -> Result<(), ContractError> {
-        let resolver = env.invoker();
-        let resolver_data: Resolver = env
-            .storage()
-            .persistent()
-            .get(&StorageKey::Resolver(resolver))
-            .ok_or(ContractError::ResolverNotFound)?;
+    fn require_active_resolver(env: &Env, resolver: &Address) -> Result<(), ContractError> {
+        let resolver_data = resolver::get_resolver(env, resolver)?;
+        resolver::check_can_fill(&resolver_data)?;
+        resolver::check_access(env, resolver)?;
 
-        if !resolver_data.active {
+        let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        if reputation::current(env, &resolver_data) < config.min_reputation {
             return Err(ContractError::ResolverNotActive);
         }
-
         Ok(())
     }
 }
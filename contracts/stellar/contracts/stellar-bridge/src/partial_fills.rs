@@ -1,126 +1,582 @@
-use soroban_sdk::{Address, BytesN, Env, Vec};
-use crate::types::{PartialFill, Swap, SwapState};
-use crate::errors::ContractError;
+use soroban_sdk::{xdr::ToXdr, Address, Bytes, BytesN, Env, Map, Vec};
+use crate::types::{FillMode, FillTerms, PartialFill, Swap, SwapState};
+use crate::error::ContractError;
 use crate::htlc::{validate_partial_fill, calculate_fill_reward};
+use crate::{Config, StorageKey};
+use stellar_bridge_leaves as leaves;
+
+pub use stellar_bridge_leaves::{MERKLE_TREE_LEGACY, MERKLE_TREE_VERSION};
+
+// Upper bound on a single get_partial_fills page
+pub const MAX_FILLS_PAGE: u32 = 50;
+
+// Resolvers a single plan_fills call weighs; each costs three reads (the
+// resolver, its approval and its suspension), which must fit the read budget
+pub const MAX_PLAN_RESOLVERS: u32 = 10;
+
+// Upper bound on fill segments for multi-secret swaps
+pub const MAX_SECRET_PARTS: u32 = 100;
+
+// Upper bound on the resolvers an order can be pre-negotiated with
+pub const MAX_EXCLUSIVE_RESOLVERS: u32 = 8;
+
+pub fn hash_leaf(env: &Env, version: u32, leaf: &BytesN<32>) -> Option<BytesN<32>> {
+    if leaves::double_hashes_leaves(version)? {
+        Some(env.crypto().keccak256(&Bytes::from_array(env, &leaf.to_array())))
+    } else {
+        Some(leaf.clone())
+    }
+}
+
+pub fn hash_pair(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    let mut data = Bytes::new(env);
+    leaves::encode_node(&mut data, &a.to_array(), &b.to_array());
+    env.crypto().keccak256(&data)
+}
 
 pub fn verify_merkle_proof(
     env: &Env,
+    version: u32,
     proof: &Vec<BytesN<32>>,
     root: &BytesN<32>,
     leaf: &BytesN<32>,
 ) -> bool {
-    let mut computed_hash = *leaf;
+    let mut computed_hash = match hash_leaf(env, version, leaf) {
+        Some(hash) => hash,
+        None => return false,
+    };
     
     for proof_element in proof.iter() {
-        computed_hash = if computed_hash.to_array() < proof_element.to_array() {
-            env.crypto().keccak256(&(computed_hash, proof_element))
-        } else {
-            env.crypto().keccak256(&(proof_element, computed_hash))
-        };
+        computed_hash = hash_pair(env, &computed_hash, &proof_element);
     }
     
     computed_hash == *root
 }
 
+pub fn fill_leaf(env: &Env, resolver: &Address, fill_amount: i128, nonce: u64) -> BytesN<32> {
+    let mut data = resolver.clone().to_xdr(env);
+    leaves::encode_fill_fields(&mut data, fill_amount, nonce);
+    env.crypto().keccak256(&data)
+}
+
+// `expires_at` is the swap's absolute timelock
+pub fn validate_fill_terms(
+    env: &Env,
+    terms: &FillTerms,
+    amount: i128,
+    expires_at: u64,
+) -> Result<(), ContractError> {
+    if terms.secret_parts > 0 && terms.fill_mode == FillMode::FillOrKill {
+        return Err(ContractError::PartialFillsNotEnabled);
+    }
+    match terms.fill_mode {
+        FillMode::AllowMultipleFills(0) => {
+            return Err(ContractError::InvalidFillTerms);
+        }
+        // Cancelling the remainder shrinks the swap, which would shift secret indices
+        FillMode::ImmediateOrCancel if terms.secret_parts > 0 => {
+            return Err(ContractError::InvalidFillTerms);
+        }
+        _ => {}
+    }
+    if terms.secret_parts > MAX_SECRET_PARTS {
+        return Err(ContractError::InvalidFillTerms);
+    }
+    if terms.min_fill_amount < 0 || terms.min_fill_amount > amount {
+        return Err(ContractError::InvalidFillTerms);
+    }
+    if let Some(step) = terms.fill_step {
+        if step <= 0 || step > amount {
+            return Err(ContractError::InvalidFillTerms);
+        }
+    }
+    if terms.exclusive_resolvers.is_empty() {
+        if terms.exclusivity_deadline != 0 {
+            return Err(ContractError::InvalidFillTerms);
+        }
+    } else if terms.exclusive_resolvers.len() > MAX_EXCLUSIVE_RESOLVERS
+        || terms.exclusivity_deadline <= env.ledger().timestamp()
+        || terms.exclusivity_deadline > expires_at
+    {
+        return Err(ContractError::InvalidFillTerms);
+    }
+    Ok(())
+}
+
+pub fn secret_leaf(env: &Env, index: u32, secret_hash: &BytesN<32>) -> BytesN<32> {
+    let mut data = Bytes::new(env);
+    leaves::encode_secret_leaf(&mut data, index, &secret_hash.to_array());
+    env.crypto().keccak256(&data)
+}
+
+// Index of the secret that unlocks a fill taking the swap to `filled + fill_amount`.
+// Secret `secret_parts` is reserved for the fill that completes the swap.
+pub fn expected_secret_index(swap: &Swap, fill_amount: i128) -> u32 {
+    let filled_after = swap.filled + fill_amount;
+    if filled_after >= swap.amount {
+        swap.secret_parts
+    } else {
+        ((filled_after - 1) * swap.secret_parts as i128 / swap.amount) as u32
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn execute_partial_fill(
     env: &Env,
+    swap_id: &BytesN<32>,
     swap: &mut Swap,
     resolver: &Address,
     fill_amount: i128,
     merkle_proof: Vec<BytesN<32>>,
     nonce: u64,
-) -> Result<i128, ContractError> {
+    rate: i128,
+) -> Result<(), ContractError> {
     // Validate the partial fill
-    validate_partial_fill(env, swap, fill_amount, resolver)?;
+    validate_partial_fill(env, swap, get_fill_count(env, swap_id), fill_amount, resolver)?;
+
+    // Multi-secret swaps are filled segment by segment via withdraw_fill_segment
+    if swap.secret_parts > 0 {
+        return Err(ContractError::InvalidSwapState);
+    }
     
     // Create leaf for merkle proof verification
-    let leaf = env.crypto().keccak256(&(resolver.clone(), fill_amount, nonce));
+    let leaf = fill_leaf(env, resolver, fill_amount, nonce);
     
     // Verify merkle proof for MEV protection
-    if !verify_merkle_proof(env, &merkle_proof, &swap.merkle_root, &leaf) {
+    if !verify_merkle_proof(env, swap.merkle_version, &merkle_proof, &swap.merkle_root, &leaf) {
         return Err(ContractError::InvalidMerkleProof);
     }
+
+    // Each leaf may only be filled once
+    let nonce_key = StorageKey::FillNonce(swap_id.clone(), resolver.clone(), nonce);
+    if env.storage().persistent().has(&nonce_key) {
+        return Err(ContractError::FillNonceAlreadyUsed);
+    }
+    env.storage().persistent().set(&nonce_key, &true);
     
-    // Update swap state
-    swap.filled += fill_amount;
+    // The slice and its reward stay escrowed until the secret is revealed in
+    // complete_swap; a refund returns both to the initiator
+    apply_fill(env, swap_id, swap, resolver, fill_amount, merkle_proof, rate);
+    swap.state = SwapState::PartialFilled;
+    let reward = take_reward(env, swap, fill_amount);
+    add_pending_payout(env, swap_id, resolver, fill_amount, reward);
+    
+    Ok(())
+}
+
+// Rewards come out of the maker fee escrowed with the swap. The fill that
+// completes the swap takes whatever is left of it, rounding dust included
+pub fn take_reward(env: &Env, swap: &mut Swap, fill_amount: i128) -> i128 {
+    let reward = if swap.filled == swap.amount {
+        swap.fee
+    } else {
+        let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+        calculate_fill_reward(fill_amount, config.resolver_reward_rate).min(swap.fee)
+    };
+    swap.fee -= reward;
+    reward
+}
+
+// Returns the segment's reward
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_fill_segment(
+    env: &Env,
+    swap_id: &BytesN<32>,
+    swap: &mut Swap,
+    resolver: &Address,
+    fill_amount: i128,
+    secret: &BytesN<32>,
+    secret_index: u32,
+    merkle_proof: Vec<BytesN<32>>,
+) -> Result<i128, ContractError> {
+    if swap.secret_parts == 0 {
+        return Err(ContractError::InvalidSwapState);
+    }
+
+    validate_partial_fill(env, swap, get_fill_count(env, swap_id), fill_amount, resolver)?;
+
+    // The secret must belong to the segment this fill lands in, and each
+    // segment's secret can only be consumed once
+    if secret_index != expected_secret_index(swap, fill_amount)
+        || (swap.filled > 0 && secret_index <= swap.last_secret_index)
+    {
+        return Err(ContractError::InvalidSecretIndex);
+    }
+
+    let secret_hash = env.crypto().keccak256(&secret.clone().into());
+    let leaf = secret_leaf(env, secret_index, &secret_hash);
+    if !verify_merkle_proof(env, swap.merkle_version, &merkle_proof, &swap.merkle_root, &leaf) {
+        return Err(ContractError::InvalidMerkleProof);
+    }
+
+    if env.storage().persistent().has(&StorageKey::UsedSecret(secret.clone())) {
+        return Err(ContractError::SecretAlreadyUsed);
+    }
+    env.storage().persistent().set(&StorageKey::UsedSecret(secret.clone()), &true);
+
+    // The segment carries its own secret, so it is released immediately
+    swap.last_secret_index = secret_index;
+    apply_fill(env, swap_id, swap, resolver, fill_amount, merkle_proof, 0);
     swap.state = if swap.filled == swap.amount {
         SwapState::Completed
     } else {
         SwapState::PartialFilled
     };
-    
-    // Calculate reward
-    let reward = calculate_fill_reward(fill_amount, 10); // 0.1% fee
-    
+    swap.released += fill_amount;
+
+    Ok(take_reward(env, swap, fill_amount))
+}
+
+fn apply_fill(
+    env: &Env,
+    swap_id: &BytesN<32>,
+    swap: &mut Swap,
+    resolver: &Address,
+    fill_amount: i128,
+    merkle_proof: Vec<BytesN<32>>,
+    rate: i128,
+) {
+    swap.filled += fill_amount;
+
     // Record the partial fill
     let partial_fill = PartialFill {
         resolver: resolver.clone(),
         amount: fill_amount,
         timestamp: env.ledger().timestamp(),
         merkle_proof,
+        rate,
     };
-    
-    // Store partial fill data (in real implementation)
-    // env.storage().persistent().set(&(PARTIAL_FILL_KEY, swap_id, resolver), &partial_fill);
-    
-    Ok(reward)
+    record_partial_fill(env, swap_id, &partial_fill);
+}
+
+pub fn get_pending_payouts(env: &Env, swap_id: &BytesN<32>) -> Map<Address, i128> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::PendingPayouts(swap_id.clone()))
+        .unwrap_or(Map::new(env))
+}
+
+// Fill rewards held in escrow alongside the payouts, credited on completion
+pub fn get_pending_rewards(env: &Env, swap_id: &BytesN<32>) -> Map<Address, i128> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::PendingRewards(swap_id.clone()))
+        .unwrap_or(Map::new(env))
+}
+
+// Removes and returns the escrowed slices and rewards owed to resolvers for a swap
+pub fn take_pending_payouts(
+    env: &Env,
+    swap_id: &BytesN<32>,
+) -> (Map<Address, i128>, Map<Address, i128>) {
+    let payouts = get_pending_payouts(env, swap_id);
+    let rewards = get_pending_rewards(env, swap_id);
+    env.storage()
+        .persistent()
+        .remove(&StorageKey::PendingPayouts(swap_id.clone()));
+    env.storage()
+        .persistent()
+        .remove(&StorageKey::PendingRewards(swap_id.clone()));
+    (payouts, rewards)
+}
+
+fn add_pending_payout(
+    env: &Env,
+    swap_id: &BytesN<32>,
+    resolver: &Address,
+    amount: i128,
+    reward: i128,
+) {
+    let mut payouts = get_pending_payouts(env, swap_id);
+    let pending = payouts.get(resolver.clone()).unwrap_or(0);
+    payouts.set(resolver.clone(), pending + amount);
+    env.storage()
+        .persistent()
+        .set(&StorageKey::PendingPayouts(swap_id.clone()), &payouts);
+
+    let mut rewards = get_pending_rewards(env, swap_id);
+    let pending = rewards.get(resolver.clone()).unwrap_or(0);
+    rewards.set(resolver.clone(), pending + reward);
+    env.storage()
+        .persistent()
+        .set(&StorageKey::PendingRewards(swap_id.clone()), &rewards);
+}
+
+pub fn get_fill_count(env: &Env, swap_id: &BytesN<32>) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::FillCount(swap_id.clone()))
+        .unwrap_or(0)
+}
+
+fn record_partial_fill(env: &Env, swap_id: &BytesN<32>, partial_fill: &PartialFill) {
+    let index = get_fill_count(env, swap_id);
+    env.storage()
+        .persistent()
+        .set(&StorageKey::PartialFill(swap_id.clone(), index), partial_fill);
+    env.storage()
+        .persistent()
+        .set(&StorageKey::FillCount(swap_id.clone()), &(index + 1));
 }
 
 pub fn get_partial_fills(
     env: &Env,
     swap_id: &BytesN<32>,
+    start: u32,
+    limit: u32,
 ) -> Vec<PartialFill> {
-    // In real implementation, retrieve all partial fills for a swap
-    // For now, return empty vector
-    Vec::new(env)
+    let mut fills = Vec::new(env);
+    let end = get_fill_count(env, swap_id).min(start.saturating_add(limit.min(MAX_FILLS_PAGE)));
+
+    for index in start..end {
+        if let Some(fill) = env
+            .storage()
+            .persistent()
+            .get(&StorageKey::PartialFill(swap_id.clone(), index))
+        {
+            fills.push_back(fill);
+        }
+    }
+
+    fills
 }
 
+// Splits `total_amount` into at most `max_slices` slices in whole lots of
+// `fill_step`, weighted per resolver. Returns one size per weight; resolvers
+// with no weight, or whose share would fall below the minimum, get 0 (the
+// lowest weights are dropped first). `caps` bounds each resolver's slice.
+// Only when `clears_remainder` (total_amount is the swap's whole unfilled
+// amount) may a slice carry dust or undercut the minimum.
+#[allow(clippy::too_many_arguments)]
 pub fn calculate_optimal_fill_sizes(
+    env: &Env,
     total_amount: i128,
-    num_resolvers: u32,
+    clears_remainder: bool,
+    weights: &Vec<i128>,
+    caps: &Vec<i128>,
+    max_slices: u32,
     min_fill_size: i128,
+    fill_step: Option<i128>,
 ) -> Vec<i128> {
-    let mut fill_sizes = Vec::new();
-    
-    if num_resolvers == 0 {
+    let n = weights.len();
+    let mut fill_sizes = zeros(env, n);
+
+    if n == 0 || max_slices == 0 || total_amount <= 0 {
         return fill_sizes;
     }
-    
-    // Calculate base fill size
-    let base_fill = total_amount / num_resolvers as i128;
-    let remainder = total_amount % num_resolvers as i128;
-    
-    // Distribute amounts ensuring minimum fill size
-    let mut remaining = total_amount;
-    for i in 0..num_resolvers {
-        let fill_size = if base_fill >= min_fill_size {
-            let extra = if (i as i128) < remainder { 1 } else { 0 };
-            base_fill + extra
-        } else if remaining >= min_fill_size {
-            min_fill_size.min(remaining)
-        } else {
-            remaining
-        };
-        
-        if fill_size > 0 {
-            fill_sizes.push(fill_size);
-            remaining -= fill_size;
+
+    // Work in whole lots; dust below one lot rides on the last slice,
+    // which clears the remainder and is exempt from size rules
+    let step = fill_step.unwrap_or(1).max(1);
+    let lots = total_amount / step;
+    let dust = total_amount % step;
+    let min_lots = ((min_fill_size + step - 1) / step).max(1);
+
+    let mut eligible = Vec::new(env);
+    for i in 0..n {
+        let cap = caps.get(i).unwrap_or(i128::MAX);
+        eligible.push_back(weights.get_unchecked(i) > 0 && cap / step >= min_lots);
+    }
+
+    // Too small to split: one remainder slice to the heaviest resolver able to take it
+    if lots < min_lots {
+        if !clears_remainder {
+            return fill_sizes;
         }
-        
-        if remaining <= 0 {
-            break;
+        let mut best: Option<u32> = None;
+        for i in 0..n {
+            let fits = caps.get(i).unwrap_or(i128::MAX) >= total_amount;
+            if weights.get_unchecked(i) > 0
+                && fits
+                && best.is_none_or(|b| weights.get_unchecked(i) > weights.get_unchecked(b))
+            {
+                best = Some(i);
+            }
         }
+        if let Some(i) = best {
+            fill_sizes.set(i, total_amount);
+        }
+        return fill_sizes;
     }
-    
+
+    // Drop the lightest resolvers until every slice clears the minimum
+    let mut lot_shares;
+    loop {
+        lot_shares = distribute_lots(env, lots, step, weights, caps, &eligible);
+
+        let mut active = 0u32;
+        let mut drop: Option<u32> = None;
+        for i in 0..n {
+            if !eligible.get_unchecked(i) {
+                continue;
+            }
+            active += 1;
+            // Ties drop the later resolver
+            if drop.is_none_or(|d| weights.get_unchecked(i) <= weights.get_unchecked(d)) {
+                drop = Some(i);
+            }
+        }
+
+        let undersized = (0..n).any(|i| eligible.get_unchecked(i) && lot_shares.get_unchecked(i) < min_lots);
+        match drop {
+            Some(d) if undersized || active > max_slices => eligible.set(d, false),
+            _ => break,
+        }
+    }
+
+    let mut allocated = 0;
+    let mut last: Option<u32> = None;
+    for i in 0..n {
+        let share = lot_shares.get_unchecked(i);
+        if eligible.get_unchecked(i) && share > 0 {
+            fill_sizes.set(i, share * step);
+            allocated += share;
+            last = Some(i);
+        }
+    }
+
+    // Dust only rides along when the slices clear the whole remainder
+    if let Some(i) = last {
+        let size = fill_sizes.get_unchecked(i);
+        if clears_remainder && allocated == lots && size + dust <= caps.get(i).unwrap_or(i128::MAX) {
+            fill_sizes.set(i, size + dust);
+        }
+    }
+
     fill_sizes
 }
 
+// Largest-remainder apportionment of `lots` by weight, water-filling any
+// resolver whose proportional share would exceed its cap
+fn distribute_lots(
+    env: &Env,
+    lots: i128,
+    step: i128,
+    weights: &Vec<i128>,
+    caps: &Vec<i128>,
+    eligible: &Vec<bool>,
+) -> Vec<i128> {
+    let n = weights.len();
+    let mut shares = zeros(env, n);
+    let mut capped = Vec::new(env);
+    for _ in 0..n {
+        capped.push_back(false);
+    }
+    let mut remaining = lots;
+
+    loop {
+        let mut total_weight = 0i128;
+        for i in 0..n {
+            if eligible.get_unchecked(i) && !capped.get_unchecked(i) {
+                total_weight += weights.get_unchecked(i);
+            }
+        }
+        if total_weight == 0 || remaining == 0 {
+            return shares;
+        }
+
+        // Fix anyone whose floor share reaches their cap, then go again
+        let mut fixed_any = false;
+        for i in 0..n {
+            if !eligible.get_unchecked(i) || capped.get_unchecked(i) {
+                continue;
+            }
+            let cap_lots = caps.get(i).unwrap_or(i128::MAX) / step;
+            if remaining * weights.get_unchecked(i) / total_weight >= cap_lots {
+                shares.set(i, cap_lots);
+                capped.set(i, true);
+                remaining -= cap_lots;
+                fixed_any = true;
+            }
+        }
+        if fixed_any {
+            continue;
+        }
+
+        let mut assigned = 0;
+        for i in 0..n {
+            if eligible.get_unchecked(i) && !capped.get_unchecked(i) {
+                let share = remaining * weights.get_unchecked(i) / total_weight;
+                shares.set(i, share);
+                assigned += share;
+            }
+        }
+
+        // Leftover lots go to the largest fractional remainders, earliest first
+        let mut leftover = remaining - assigned;
+        let mut bumped = Vec::new(env);
+        for _ in 0..n {
+            bumped.push_back(false);
+        }
+        while leftover > 0 {
+            let mut best: Option<(u32, i128)> = None;
+            for i in 0..n {
+                if !eligible.get_unchecked(i) || capped.get_unchecked(i) || bumped.get_unchecked(i) {
+                    continue;
+                }
+                let fraction = remaining * weights.get_unchecked(i) % total_weight;
+                if best.is_none_or(|(_, f)| fraction > f) {
+                    best = Some((i, fraction));
+                }
+            }
+            match best {
+                Some((i, _)) => {
+                    shares.set(i, shares.get_unchecked(i) + 1);
+                    bumped.set(i, true);
+                    leftover -= 1;
+                }
+                None => break,
+            }
+        }
+
+        return shares;
+    }
+}
+
+fn zeros(env: &Env, n: u32) -> Vec<i128> {
+    let mut v = Vec::new(env);
+    for _ in 0..n {
+        v.push_back(0);
+    }
+    v
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use soroban_sdk::testutils::{Address as _, BytesN as _};
     use soroban_sdk::{vec, Env};
 
+    // Equal weights, no caps, one slice per resolver at most; zero slices dropped
+    fn even_fill_sizes(
+        env: &Env,
+        total_amount: i128,
+        num_resolvers: u32,
+        min_fill_size: i128,
+        fill_step: Option<i128>,
+    ) -> Vec<i128> {
+        let mut weights = Vec::new(env);
+        for _ in 0..num_resolvers {
+            weights.push_back(1);
+        }
+        let sizes = calculate_optimal_fill_sizes(
+            env,
+            total_amount,
+            true,
+            &weights,
+            &Vec::new(env),
+            num_resolvers,
+            min_fill_size,
+            fill_step,
+        );
+
+        let mut slices = Vec::new(env);
+        for size in sizes.iter().filter(|size| *size > 0) {
+            slices.push_back(size);
+        }
+        slices
+    }
+
     #[test]
     fn test_verify_merkle_proof_single() {
         let env = Env::default();
@@ -128,7 +584,7 @@ mod tests {
         let proof = vec![&env];
         
         // Single leaf tree - root equals leaf
-        assert!(verify_merkle_proof(&env, &proof, &leaf, &leaf));
+        assert!(verify_merkle_proof(&env, MERKLE_TREE_LEGACY, &proof, &leaf, &leaf));
     }
 
     #[test]
@@ -138,47 +594,356 @@ mod tests {
         let leaf2 = BytesN::random(&env);
         
         // Calculate root for two-leaf tree
-        let root = if leaf1.to_array() < leaf2.to_array() {
-            env.crypto().keccak256(&(leaf1, leaf2))
+        let (first, second) = if leaf1.to_array() < leaf2.to_array() {
+            (leaf1.clone(), leaf2.clone())
         } else {
-            env.crypto().keccak256(&(leaf2, leaf1))
+            (leaf2.clone(), leaf1.clone())
         };
+        let mut data = Bytes::from_array(&env, &first.to_array());
+        data.extend_from_array(&second.to_array());
+        let root = env.crypto().keccak256(&data);
         
         let proof = vec![&env, leaf2];
-        assert!(verify_merkle_proof(&env, &proof, &root, &leaf1));
+        assert!(verify_merkle_proof(&env, MERKLE_TREE_LEGACY, &proof, &root, &leaf1));
+    }
+
+    #[test]
+    fn test_leaves_are_domain_separated() {
+        let env = Env::default();
+        let leaf1 = BytesN::random(&env);
+        let leaf2 = BytesN::random(&env);
+        let leaf3 = BytesN::random(&env);
+        let h = |leaf: &BytesN<32>| hash_leaf(&env, MERKLE_TREE_VERSION, leaf).unwrap();
+
+        let node = hash_pair(&env, &h(&leaf1), &h(&leaf2));
+        let root = hash_pair(&env, &node, &h(&leaf3));
+        assert!(verify_merkle_proof(&env, MERKLE_TREE_VERSION, &vec![&env, h(&leaf2), h(&leaf3)], &root, &leaf1));
+
+        // The internal node only passes as a leaf in legacy trees
+        let legacy_root = hash_pair(&env, &hash_pair(&env, &leaf1, &leaf2), &leaf3);
+        let legacy_node = hash_pair(&env, &leaf1, &leaf2);
+        assert!(verify_merkle_proof(&env, MERKLE_TREE_LEGACY, &vec![&env, leaf3.clone()], &legacy_root, &legacy_node));
+        assert!(!verify_merkle_proof(&env, MERKLE_TREE_VERSION, &vec![&env, h(&leaf3)], &root, &node));
+
+        // Unknown tree versions never verify
+        assert!(!verify_merkle_proof(&env, 2, &vec![&env], &leaf1, &leaf1));
+    }
+
+    #[test]
+    fn test_fill_leaf_binds_all_fields() {
+        let env = Env::default();
+        let resolver = Address::generate(&env);
+        let other = Address::generate(&env);
+        let leaf = fill_leaf(&env, &resolver, 1000, 1);
+
+        assert_eq!(leaf, fill_leaf(&env, &resolver, 1000, 1));
+        assert_ne!(leaf, fill_leaf(&env, &other, 1000, 1));
+        assert_ne!(leaf, fill_leaf(&env, &resolver, 1001, 1));
+        assert_ne!(leaf, fill_leaf(&env, &resolver, 1000, 2));
+
+        // A proof for one leaf must not verify a sibling leaf
+        let sibling = fill_leaf(&env, &other, 500, 2);
+        let root = hash_pair(&env, &leaf, &sibling);
+        assert!(verify_merkle_proof(&env, MERKLE_TREE_LEGACY, &vec![&env, sibling.clone()], &root, &leaf));
+        assert!(!verify_merkle_proof(
+            &env,
+            MERKLE_TREE_LEGACY,
+            &vec![&env, sibling],
+            &root,
+            &fill_leaf(&env, &resolver, 2000, 1)
+        ));
+    }
+
+    #[test]
+    fn test_expected_secret_index() {
+        let env = Env::default();
+        let mut swap = Swap {
+            initiator: Address::generate(&env),
+            token: Address::generate(&env),
+            amount: 1000,
+            filled: 0,
+            secret_hash: BytesN::random(&env),
+            timelock: 0,
+            ethereum_address: Bytes::new(&env),
+            state: SwapState::Initiated,
+            fill_mode: FillMode::AllowPartial,
+            merkle_root: BytesN::random(&env),
+            merkle_version: 0,
+            created_at: 0,
+            secret_parts: 4,
+            last_secret_index: 0,
+            released: 0,
+            fee: 0,
+            min_fill_amount: 0,
+            fill_step: None,
+            exclusive_resolvers: Vec::new(&env),
+            exclusivity_deadline: 0,
+        };
+
+        // Segment boundaries belong to the lower segment
+        assert_eq!(expected_secret_index(&swap, 1), 0);
+        assert_eq!(expected_secret_index(&swap, 250), 0);
+        assert_eq!(expected_secret_index(&swap, 251), 1);
+        assert_eq!(expected_secret_index(&swap, 999), 3);
+        // Completing fill uses the extra secret
+        assert_eq!(expected_secret_index(&swap, 1000), 4);
+
+        swap.filled = 600;
+        assert_eq!(expected_secret_index(&swap, 100), 2);
+        assert_eq!(expected_secret_index(&swap, 400), 4);
     }
 
     #[test]
     fn test_calculate_optimal_fill_sizes() {
+        let env = Env::default();
+
         // Test even distribution
-        let fills = calculate_optimal_fill_sizes(1000, 4, 100);
+        let fills = even_fill_sizes(&env, 1000, 4, 100, None);
         assert_eq!(fills.len(), 4);
         assert_eq!(fills.iter().sum::<i128>(), 1000);
         
         // Test with remainder
-        let fills = calculate_optimal_fill_sizes(1001, 4, 100);
+        let fills = even_fill_sizes(&env, 1001, 4, 100, None);
         assert_eq!(fills.len(), 4);
         assert_eq!(fills.iter().sum::<i128>(), 1001);
         
         // Test minimum fill size constraint
-        let fills = calculate_optimal_fill_sizes(50, 4, 100);
+        let fills = even_fill_sizes(&env, 50, 4, 100, None);
         assert_eq!(fills.len(), 1);
-        assert_eq!(fills[0], 50);
+        assert_eq!(fills.get(0), Some(50));
+
+        // Never produces slices below the minimum
+        let fills = even_fill_sizes(&env, 350, 4, 100, None);
+        assert_eq!(fills.len(), 3);
+        assert_eq!(fills.iter().sum::<i128>(), 350);
+        assert!(fills.iter().all(|f| f >= 100));
+    }
+
+    #[test]
+    fn test_calculate_optimal_fill_sizes_with_step() {
+        let env = Env::default();
+
+        // Slices are whole lots, dust rides on the last slice
+        let fills = even_fill_sizes(&env, 1_050, 3, 100, Some(100));
+        assert_eq!(fills, vec![&env, 400, 300, 350]);
+
+        // Minimum is rounded up to whole lots
+        let fills = even_fill_sizes(&env, 1_000, 10, 150, Some(100));
+        assert_eq!(fills, vec![&env, 200, 200, 200, 200, 200]);
+
+        // Less than one lot is a single remainder slice
+        let fills = even_fill_sizes(&env, 60, 3, 10, Some(100));
+        assert_eq!(fills, vec![&env, 60]);
     }
 
     #[test]
     fn test_edge_cases() {
+        let env = Env::default();
+
         // Zero amount
-        let fills = calculate_optimal_fill_sizes(0, 4, 100);
+        let fills = even_fill_sizes(&env, 0, 4, 100, None);
         assert_eq!(fills.len(), 0);
         
         // Zero resolvers
-        let fills = calculate_optimal_fill_sizes(1000, 0, 100);
+        let fills = even_fill_sizes(&env, 1000, 0, 100, None);
         assert_eq!(fills.len(), 0);
         
         // Single resolver
-        let fills = calculate_optimal_fill_sizes(1000, 1, 100);
+        let fills = even_fill_sizes(&env, 1000, 1, 100, None);
         assert_eq!(fills.len(), 1);
-        assert_eq!(fills[0], 1000);
+        assert_eq!(fills.get(0), Some(1000));
+    }
+
+    #[test]
+    fn test_weighted_fill_sizes() {
+        let env = Env::default();
+        let weights = vec![&env, 3, 1, 0, 1];
+
+        // Proportional to weight; zero weights get nothing
+        let sizes = calculate_optimal_fill_sizes(&env, 1_000, true, &weights, &Vec::new(&env), 4, 100, None);
+        assert_eq!(sizes, vec![&env, 600, 200, 0, 200]);
+
+        // Capped resolvers spill over to the rest
+        let caps = vec![&env, 400, i128::MAX, i128::MAX, i128::MAX];
+        let sizes = calculate_optimal_fill_sizes(&env, 1_000, true, &weights, &caps, 4, 100, None);
+        assert_eq!(sizes, vec![&env, 400, 300, 0, 300]);
+
+        // Slice count limits drop the lightest resolvers, later ones first
+        let sizes = calculate_optimal_fill_sizes(&env, 1_000, true, &weights, &Vec::new(&env), 2, 100, None);
+        assert_eq!(sizes, vec![&env, 750, 250, 0, 0]);
+    }
+
+    #[test]
+    fn test_fill_sizes_short_of_remainder() {
+        let env = Env::default();
+        let weights = vec![&env, 1];
+
+        // 10_500 swap with 2_000 reserved: the 500 dust stays with the reservations
+        let sizes = calculate_optimal_fill_sizes(&env, 8_500, false, &weights, &Vec::new(&env), 1, 0, Some(1_000));
+        assert_eq!(sizes, vec![&env, 8_000]);
+
+        // Nor is an undersized lone slice offered
+        let sizes = calculate_optimal_fill_sizes(&env, 150, false, &weights, &Vec::new(&env), 1, 200, None);
+        assert_eq!(sizes, vec![&env, 0]);
+    }
+}
+
+// Vectors shared with contracts/ethereum/contracts/libraries/MerkleProof.sol
+#[cfg(test)]
+mod merkle_vectors {
+    extern crate std;
+
+    use super::*;
+    use serde_json::Value;
+
+    const VECTORS: &str = include_str!("../../../../test-vectors/merkle.json");
+
+    fn bytes32(env: &Env, value: &Value) -> BytesN<32> {
+        let hex = value.as_str().unwrap().trim_start_matches("0x");
+        let mut out = [0u8; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        BytesN::from_array(env, &out)
+    }
+
+    fn proof(env: &Env, value: &Value) -> Vec<BytesN<32>> {
+        let mut proof = Vec::new(env);
+        for element in value.as_array().unwrap() {
+            proof.push_back(bytes32(env, element));
+        }
+        proof
+    }
+
+    #[test]
+    fn test_shared_merkle_vectors() {
+        let env = Env::default();
+        let vectors: Value = serde_json::from_str(VECTORS).unwrap();
+
+        for tree in vectors["trees"].as_array().unwrap() {
+            let root = bytes32(&env, &tree["root"]);
+            for leaf in tree["leaves"].as_array().unwrap() {
+                assert!(verify_merkle_proof(
+                    &env,
+                    MERKLE_TREE_VERSION,
+                    &proof(&env, &leaf["proof"]),
+                    &root,
+                    &bytes32(&env, &leaf["leafData"]),
+                ));
+            }
+        }
+
+        for case in vectors["invalid"].as_array().unwrap() {
+            assert!(!verify_merkle_proof(
+                &env,
+                MERKLE_TREE_VERSION,
+                &proof(&env, &case["proof"]),
+                &bytes32(&env, &case["root"]),
+                &bytes32(&env, &case["leafData"]),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    extern crate std;
+
+    use super::*;
+    use proptest::prelude::*;
+    use std::vec::Vec as StdVec;
+
+    fn plan(
+        total: i128,
+        clears_remainder: bool,
+        weights: &[i128],
+        caps: &[i128],
+        max_slices: u32,
+        min_fill: i128,
+        step: Option<i128>,
+    ) -> StdVec<i128> {
+        let env = Env::default();
+        let mut weight_vec = Vec::new(&env);
+        for w in weights {
+            weight_vec.push_back(*w);
+        }
+        let mut cap_vec = Vec::new(&env);
+        for c in caps {
+            cap_vec.push_back(*c);
+        }
+        calculate_optimal_fill_sizes(&env, total, clears_remainder, &weight_vec, &cap_vec, max_slices, min_fill, step)
+            .iter()
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_uncapped_slices_sum_exactly(
+            total in 1i128..1_000_000,
+            weights in proptest::collection::vec(1i128..10_000, 1..8),
+            max_slices in 1u32..8,
+            min_ratio in 0i128..=100,
+            step in proptest::option::of(1i128..1_000),
+        ) {
+            let min_fill = total * min_ratio / 100;
+            let sizes = plan(total, true, &weights, &[], max_slices, min_fill, step);
+            let slices: StdVec<i128> = sizes.iter().copied().filter(|s| *s > 0).collect();
+
+            prop_assert_eq!(sizes.len(), weights.len());
+            prop_assert_eq!(slices.iter().sum::<i128>(), total);
+            prop_assert!(slices.len() as u32 <= max_slices);
+
+            // Only a lone slice clearing the remainder may undercut the minimum
+            if slices.len() > 1 {
+                prop_assert!(slices.iter().all(|s| *s >= min_fill));
+            }
+        }
+
+        #[test]
+        fn prop_capped_slices_never_exceed_remaining(
+            total in 1i128..1_000_000,
+            weighted_caps in proptest::collection::vec((0i128..10_000, 0i128..500_000), 1..8),
+            min_fill in 0i128..10_000,
+            step in proptest::option::of(1i128..1_000),
+        ) {
+            let weights: StdVec<i128> = weighted_caps.iter().map(|(w, _)| *w).collect();
+            let caps: StdVec<i128> = weighted_caps.iter().map(|(_, c)| *c).collect();
+            let sizes = plan(total, true, &weights, &caps, weights.len() as u32, min_fill, step);
+            let slices = sizes.iter().filter(|s| **s > 0).count();
+
+            prop_assert!(sizes.iter().sum::<i128>() <= total);
+            for (i, size) in sizes.iter().enumerate() {
+                prop_assert!(*size <= caps[i]);
+                if *size > 0 {
+                    prop_assert!(weights[i] > 0);
+                    prop_assert!(*size >= min_fill || (slices == 1 && *size == total));
+                }
+            }
+        }
+
+        // Short of the remainder (reservations outstanding) every slice must
+        // pass validate_partial_fill on its own: whole lots, at least the minimum
+        #[test]
+        fn prop_slices_short_of_remainder_obey_size_rules(
+            total in 1i128..1_000_000,
+            weighted_caps in proptest::collection::vec((0i128..10_000, 0i128..500_000), 1..8),
+            min_fill in 0i128..10_000,
+            step in proptest::option::of(1i128..1_000),
+        ) {
+            let weights: StdVec<i128> = weighted_caps.iter().map(|(w, _)| *w).collect();
+            let caps: StdVec<i128> = weighted_caps.iter().map(|(_, c)| *c).collect();
+            let sizes = plan(total, false, &weights, &caps, weights.len() as u32, min_fill, step);
+
+            prop_assert!(sizes.iter().sum::<i128>() <= total);
+            for (i, size) in sizes.iter().enumerate() {
+                prop_assert!(*size <= caps[i]);
+                if *size > 0 {
+                    prop_assert!(*size >= min_fill);
+                    if let Some(step) = step {
+                        prop_assert_eq!(*size % step, 0);
+                    }
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,105 @@
+use soroban_sdk::{Address, Env, Symbol};
+use crate::jailing;
+use crate::resolver::{get_resolver, set_resolver};
+use crate::types::Resolver;
+
+// Reputation is scored in 0..=MAX_REPUTATION and starts at BASE_REPUTATION.
+// Each outcome moves it by a fixed step. Between outcomes its distance from
+// the base decays hyperbolically, halving after REPUTATION_HALF_LIFE:
+//
+//   decayed = BASE + (reputation - BASE) * HALF_LIFE / (HALF_LIFE + elapsed)
+//
+// so old outcomes fade and an idle resolver drifts back toward the base.
+pub const BASE_REPUTATION: u32 = 1000;
+pub const MAX_REPUTATION: u32 = 10000;
+pub const REPUTATION_HALF_LIFE: u64 = 30 * 24 * 60 * 60;
+
+// success_rate is an exponential moving average of outcomes in basis points,
+// each new outcome weighted 1 / SUCCESS_RATE_WINDOW
+pub const SUCCESS_RATE_WINDOW: u32 = 10;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    FillCompleted,
+    ReservationExpired,
+    Slashed,
+    RefundedAfterFill,
+}
+
+impl Outcome {
+    fn reputation_delta(self) -> i64 {
+        match self {
+            Outcome::FillCompleted => 10,
+            Outcome::ReservationExpired => -50,
+            Outcome::RefundedAfterFill => -100,
+            Outcome::Slashed => -200,
+        }
+    }
+
+    fn is_success(self) -> bool {
+        matches!(self, Outcome::FillCompleted)
+    }
+
+    fn reason(self, env: &Env) -> Symbol {
+        match self {
+            Outcome::FillCompleted => Symbol::new(env, "fill_completed"),
+            Outcome::ReservationExpired => Symbol::new(env, "reservation_expired"),
+            Outcome::RefundedAfterFill => Symbol::new(env, "refunded_after_fill"),
+            Outcome::Slashed => Symbol::new(env, "slashed"),
+        }
+    }
+}
+
+pub fn decayed(reputation: u32, elapsed: u64) -> u32 {
+    let base = BASE_REPUTATION as i128;
+    let distance = reputation as i128 - base;
+    let half_life = REPUTATION_HALF_LIFE as i128;
+    (base + distance * half_life / (half_life + elapsed as i128)) as u32
+}
+
+// Reputation as of the current ledger time
+pub fn current(env: &Env, data: &Resolver) -> u32 {
+    let elapsed = env.ledger().timestamp().saturating_sub(data.last_outcome_at);
+    decayed(data.reputation, elapsed)
+}
+
+pub fn apply(env: &Env, resolver: &Address, data: &mut Resolver, outcome: Outcome) {
+    let reputation = current(env, data) as i64 + outcome.reputation_delta();
+    data.reputation = reputation.clamp(0, MAX_REPUTATION as i64) as u32;
+
+    let sample = if outcome.is_success() { 10000 } else { 0 };
+    data.success_rate =
+        (data.success_rate * (SUCCESS_RATE_WINDOW - 1) + sample) / SUCCESS_RATE_WINDOW;
+    data.last_outcome_at = env.ledger().timestamp();
+    if !outcome.is_success() {
+        jailing::record_failure(env, resolver, data, outcome.reason(env));
+    }
+
+    env.events().publish(
+        (Symbol::new(env, "reputation_updated"), resolver.clone()),
+        (data.reputation, data.success_rate),
+    );
+}
+
+// Score an outcome for `resolver`; addresses that never registered are ignored
+pub fn record(env: &Env, resolver: &Address, outcome: Outcome) {
+    let Ok(mut data) = get_resolver(env, resolver) else {
+        return;
+    };
+    apply(env, resolver, &mut data, outcome);
+    set_resolver(env, resolver, &data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reputation_decay() {
+        assert_eq!(decayed(2000, 0), 2000);
+        assert_eq!(decayed(2000, REPUTATION_HALF_LIFE), 1500);
+        assert_eq!(decayed(0, REPUTATION_HALF_LIFE), 500);
+        assert_eq!(decayed(0, 3 * REPUTATION_HALF_LIFE), 750);
+        assert_eq!(decayed(BASE_REPUTATION, u64::MAX / 2), BASE_REPUTATION);
+    }
+}
@@ -0,0 +1,227 @@
+use soroban_sdk::{Address, BytesN, Env, Map, Symbol, Vec};
+use crate::error::ContractError;
+use crate::partial_fills::{fill_leaf, verify_merkle_proof};
+use crate::reputation::{self, Outcome};
+use crate::resolver;
+use crate::slashing;
+use crate::types::{FillReservation, Swap, SwapReservations};
+use crate::{Config, StorageKey};
+
+// Defaults applied at initialization, adjustable by the admin
+pub const DEFAULT_RESERVATION_PERIOD: u64 = 300;
+pub const DEFAULT_RESERVATION_BOND_RATE: u32 = 100;
+
+// Upper bound on the exclusivity window
+pub const MAX_RESERVATION_PERIOD: u64 = 3600;
+
+pub fn get_reservations(env: &Env, swap_id: &BytesN<32>) -> Map<Address, FillReservation> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::Reservations(swap_id.clone()))
+        .unwrap_or(Map::new(env))
+}
+
+fn set_reservations(env: &Env, swap_id: &BytesN<32>, reservations: &Map<Address, FillReservation>) {
+    let key = StorageKey::Reservations(swap_id.clone());
+    if reservations.is_empty() {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, reservations);
+    }
+}
+
+pub fn is_expired(env: &Env, reservation: &FillReservation) -> bool {
+    env.ledger().timestamp() >= reservation.expires_at
+}
+
+// Amount held by live reservations, excluding `except`'s own
+fn reserved_amount(
+    env: &Env,
+    reservations: &Map<Address, FillReservation>,
+    except: Option<&Address>,
+) -> i128 {
+    let mut reserved = 0;
+    for (holder, reservation) in reservations.iter() {
+        if Some(&holder) != except && !is_expired(env, &reservation) {
+            reserved += reservation.amount;
+        }
+    }
+    reserved
+}
+
+pub fn view(env: &Env, swap_id: &BytesN<32>, swap: &Swap) -> SwapReservations {
+    let reservations = get_reservations(env, swap_id);
+    let reserved = reserved_amount(env, &reservations, None);
+    SwapReservations {
+        available: (swap.amount - swap.filled - reserved).max(0),
+        reserved,
+        reservations,
+    }
+}
+
+// Lock a slice of the swap for `resolver`, bonded by stake in proportion to
+// the share of the swap reserved. Only fills committed to in the swap's merkle
+// tree can be reserved, and the leaf is derived here from the resolver, amount
+// and nonce so a reservation always matches the fill it claims to precede.
+pub fn reserve(
+    env: &Env,
+    swap_id: &BytesN<32>,
+    swap: &Swap,
+    resolver: &Address,
+    amount: i128,
+    nonce: u64,
+    merkle_proof: Vec<BytesN<32>>,
+) -> Result<FillReservation, ContractError> {
+    // Segments of multi-secret swaps are claimed by revealing their secret
+    if swap.secret_parts > 0 {
+        return Err(ContractError::InvalidSwapState);
+    }
+    let commitment = fill_leaf(env, resolver, amount, nonce);
+    if !verify_merkle_proof(env, swap.merkle_version, &merkle_proof, &swap.merkle_root, &commitment) {
+        return Err(ContractError::InvalidMerkleProof);
+    }
+    if env
+        .storage()
+        .persistent()
+        .has(&StorageKey::FillNonce(swap_id.clone(), resolver.clone(), nonce))
+    {
+        return Err(ContractError::FillNonceAlreadyUsed);
+    }
+
+    expire(env, swap_id, swap);
+
+    let mut reservations = get_reservations(env, swap_id);
+    if reservations.contains_key(resolver.clone()) {
+        return Err(ContractError::ReservationExists);
+    }
+    if amount > swap.amount - swap.filled - reserved_amount(env, &reservations, None) {
+        return Err(ContractError::InsufficientCapacity);
+    }
+
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut resolver_data = resolver::get_resolver(env, resolver)?;
+
+    // The bond is set aside from the resolver's stake until the reservation settles
+    let bond = bond_for(&config, swap, amount)?;
+    if bond > resolver_data.stake {
+        return Err(ContractError::InsufficientStake);
+    }
+    resolver_data.stake -= bond;
+    resolver::set_resolver(env, resolver, &resolver_data);
+
+    let reservation = FillReservation {
+        amount,
+        bond,
+        commitment,
+        expires_at: env.ledger().timestamp() + config.reservation_period,
+    };
+    reservations.set(resolver.clone(), reservation.clone());
+    set_reservations(env, swap_id, &reservations);
+
+    Ok(reservation)
+}
+
+// Bonds are in stake units: reservation_bond_rate of min_stake for the whole
+// swap, prorated by the reserved share and rounded up so every reservation
+// costs something
+fn bond_for(config: &Config, swap: &Swap, amount: i128) -> Result<i128, ContractError> {
+    let bond = config
+        .min_stake
+        .checked_mul(config.reservation_bond_rate as i128)
+        .and_then(|value| value.checked_mul(amount))
+        .ok_or(ContractError::CalculationOverflow)?;
+    let whole = swap
+        .amount
+        .checked_mul(10000)
+        .ok_or(ContractError::CalculationOverflow)?;
+    Ok((bond + whole - 1) / whole)
+}
+
+// Check a fill against live reservations. A resolver's own reservation must
+// match the revealed fill and is released back to their stake; everyone else
+// is limited to the unreserved capacity.
+pub fn consume(
+    env: &Env,
+    swap_id: &BytesN<32>,
+    swap: &Swap,
+    resolver: &Address,
+    fill_amount: i128,
+    leaf: &BytesN<32>,
+) -> Result<(), ContractError> {
+    expire(env, swap_id, swap);
+
+    let mut reservations = get_reservations(env, swap_id);
+    if let Some(reservation) = reservations.get(resolver.clone()) {
+        if reservation.amount != fill_amount || reservation.commitment != *leaf {
+            return Err(ContractError::ReservationMismatch);
+        }
+        return_bond(env, resolver, reservation.bond);
+        reservations.remove(resolver.clone());
+        set_reservations(env, swap_id, &reservations);
+        return Ok(());
+    }
+
+    if fill_amount > swap.amount - swap.filled - reserved_amount(env, &reservations, Some(resolver)) {
+        return Err(ContractError::InsufficientCapacity);
+    }
+
+    Ok(())
+}
+
+// Slash the bonds of lapsed reservations, split between the swap initiator
+// and the insurance fund
+pub fn expire(env: &Env, swap_id: &BytesN<32>, swap: &Swap) -> i128 {
+    let mut reservations = get_reservations(env, swap_id);
+    let mut slashed = 0;
+
+    for (holder, reservation) in reservations.clone().iter() {
+        if !is_expired(env, &reservation) {
+            continue;
+        }
+        reservations.remove(holder.clone());
+        slashed += reservation.bond;
+
+        if reservation.bond > 0 {
+            slashing::distribute(
+                env,
+                &holder,
+                swap_id,
+                &swap.initiator,
+                reservation.bond,
+                Symbol::new(env, "reservation_expired"),
+                &env.current_contract_address(),
+            );
+        }
+
+        reputation::record(env, &holder, Outcome::ReservationExpired);
+
+        env.events().publish(
+            (Symbol::new(env, "reservation_expired"),),
+            (swap_id.clone(), holder, reservation.bond),
+        );
+    }
+
+    set_reservations(env, swap_id, &reservations);
+
+    slashed
+}
+
+// Settle every reservation once the swap can no longer be filled: lapsed ones
+// are slashed, live ones get their bond back
+pub fn release_all(env: &Env, swap_id: &BytesN<32>, swap: &Swap) {
+    expire(env, swap_id, swap);
+
+    for (holder, reservation) in get_reservations(env, swap_id).iter() {
+        return_bond(env, &holder, reservation.bond);
+    }
+    env.storage()
+        .persistent()
+        .remove(&StorageKey::Reservations(swap_id.clone()));
+}
+
+fn return_bond(env: &Env, resolver: &Address, bond: i128) {
+    if let Ok(mut resolver_data) = resolver::get_resolver(env, resolver) {
+        resolver_data.stake += bond;
+        resolver::set_resolver(env, resolver, &resolver_data);
+    }
+}
@@ -0,0 +1,340 @@
+use soroban_sdk::{token, Address, Env, Vec};
+use crate::error::ContractError;
+use crate::delegation;
+use crate::jailing;
+use crate::reputation;
+use crate::types::{Resolver, ResolverProfile, ResolverSort};
+use crate::{Config, StorageKey};
+
+// Default wait between request_unstake and withdraw_stake
+pub const DEFAULT_UNBONDING_PERIOD: u64 = 7 * 24 * 60 * 60;
+
+// Resolvers read per get_top_resolvers page, keeping its reads (the index slot
+// and the resolver for each) inside a transaction's read budget
+pub const MAX_RESOLVERS_PAGE: u32 = 15;
+
+// Operator keys a resolver can hold at once
+pub const MAX_OPERATORS: u32 = 5;
+
+// Profile size limits, in bytes for strings and entries for lists
+pub const MAX_DISPLAY_NAME_LEN: u32 = 64;
+pub const MAX_API_URI_LEN: u32 = 256;
+pub const MAX_SUPPORTED_TOKENS: u32 = 32;
+pub const MAX_CHAIN_IDS: u32 = 32;
+
+pub fn get_resolver(env: &Env, resolver: &Address) -> Result<Resolver, ContractError> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::Resolver(resolver.clone()))
+        .ok_or(ContractError::ResolverNotFound)
+}
+
+// Every write goes through here so the active count follows the active flag
+pub fn set_resolver(env: &Env, resolver: &Address, data: &Resolver) {
+    let key = StorageKey::Resolver(resolver.clone());
+    let was_active = env
+        .storage()
+        .persistent()
+        .get::<_, Resolver>(&key)
+        .is_some_and(|previous| previous.active);
+    if was_active != data.active {
+        let count = get_active_count(env);
+        let count = if data.active { count + 1 } else { count - 1 };
+        env.storage().instance().set(&StorageKey::ActiveResolverCount, &count);
+    }
+    env.storage().persistent().set(&key, data);
+}
+
+pub fn get_resolver_count(env: &Env) -> u32 {
+    env.storage().instance().get(&StorageKey::ResolverCount).unwrap_or(0)
+}
+
+// Append a newly registered resolver, returning the number registered
+pub fn add_to_index(env: &Env, resolver: &Address) -> u32 {
+    let count = get_resolver_count(env);
+    env.storage()
+        .persistent()
+        .set(&StorageKey::ResolverIndex(count), resolver);
+    env.storage().instance().set(&StorageKey::ResolverCount, &(count + 1));
+    count + 1
+}
+
+pub fn get_active_count(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&StorageKey::ActiveResolverCount)
+        .unwrap_or(0)
+}
+
+fn sort_key(env: &Env, data: &Resolver, sort_by: &ResolverSort) -> i128 {
+    match sort_by {
+        ResolverSort::Reputation => reputation::current(env, data) as i128,
+        ResolverSort::Volume => data.total_volume,
+        ResolverSort::Stake => delegation::effective_stake(data),
+    }
+}
+
+// Active resolvers in one page of the index ranked highest first; ties keep
+// registration order
+pub fn top_resolvers(
+    env: &Env,
+    start: u32,
+    limit: u32,
+    count: u32,
+    sort_by: &ResolverSort,
+) -> Vec<Address> {
+    let end = get_resolver_count(env).min(start.saturating_add(limit.min(MAX_RESOLVERS_PAGE)));
+    let mut ranked: Vec<(i128, Address)> = Vec::new(env);
+    for index in start..end {
+        let Some(resolver) = env
+            .storage()
+            .persistent()
+            .get::<_, Address>(&StorageKey::ResolverIndex(index))
+        else {
+            continue;
+        };
+        let Ok(data) = get_resolver(env, &resolver) else {
+            continue;
+        };
+        if !data.active {
+            continue;
+        }
+        let key = sort_key(env, &data, sort_by);
+
+        // Insertion into the ranked list, bounded to `count` entries
+        let mut position = ranked.len();
+        while position > 0 && ranked.get(position - 1).unwrap().0 < key {
+            position -= 1;
+        }
+        if position < count {
+            ranked.insert(position, (key, resolver));
+            if ranked.len() > count {
+                ranked.pop_back();
+            }
+        }
+    }
+
+    let mut top = Vec::new(env);
+    for (_, resolver) in ranked.iter() {
+        top.push_back(resolver);
+    }
+    top
+}
+
+// Admin decision on an address: Some(true) approved, Some(false) revoked,
+// None for resolvers that registered before any decision was made
+pub fn get_approval(env: &Env, resolver: &Address) -> Option<bool> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::ResolverApproval(resolver.clone()))
+}
+
+pub fn set_approval(env: &Env, resolver: &Address, approved: bool) {
+    env.storage()
+        .persistent()
+        .set(&StorageKey::ResolverApproval(resolver.clone()), &approved);
+}
+
+pub fn is_suspended(env: &Env, resolver: &Address) -> bool {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::ResolverSuspended(resolver.clone()))
+        .unwrap_or(false)
+}
+
+pub fn set_suspended(env: &Env, resolver: &Address, suspended: bool) {
+    let key = StorageKey::ResolverSuspended(resolver.clone());
+    if suspended {
+        env.storage().persistent().set(&key, &true);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+}
+
+// Whether the address may register; whitelist mode requires an explicit approval
+pub fn check_can_register(env: &Env, resolver: &Address, whitelist_mode: bool) -> Result<(), ContractError> {
+    let approval = get_approval(env, resolver);
+    if approval == Some(false) || is_suspended(env, resolver) {
+        return Err(ContractError::Unauthorized);
+    }
+    if whitelist_mode && approval.is_none() {
+        return Err(ContractError::Unauthorized);
+    }
+    Ok(())
+}
+
+// Revoked and suspended resolvers are out of rotation whatever the mode;
+// resolvers registered before whitelist mode keep working
+pub fn check_access(env: &Env, resolver: &Address) -> Result<(), ContractError> {
+    if get_approval(env, resolver) == Some(false) || is_suspended(env, resolver) {
+        return Err(ContractError::ResolverNotActive);
+    }
+    Ok(())
+}
+
+pub fn get_operators(env: &Env, resolver: &Address) -> Vec<Address> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::Operators(resolver.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+fn set_operators(env: &Env, resolver: &Address, operators: &Vec<Address>) {
+    let key = StorageKey::Operators(resolver.clone());
+    if operators.is_empty() {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, operators);
+    }
+}
+
+// Resolver an operator key acts for
+pub fn owner_of(env: &Env, operator: &Address) -> Option<Address> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::OperatorOf(operator.clone()))
+}
+
+// An operator serves a single resolver and can't itself be a resolver
+pub fn add_operator(env: &Env, resolver: &Address, operator: &Address) -> Result<(), ContractError> {
+    get_resolver(env, resolver)?;
+    if operator == resolver
+        || owner_of(env, operator).is_some()
+        || env.storage().persistent().has(&StorageKey::Resolver(operator.clone()))
+    {
+        return Err(ContractError::InvalidAddress);
+    }
+
+    let mut operators = get_operators(env, resolver);
+    if operators.len() >= MAX_OPERATORS {
+        return Err(ContractError::LimitExceeded);
+    }
+    operators.push_back(operator.clone());
+    set_operators(env, resolver, &operators);
+    env.storage()
+        .persistent()
+        .set(&StorageKey::OperatorOf(operator.clone()), resolver);
+    Ok(())
+}
+
+pub fn remove_operator(env: &Env, resolver: &Address, operator: &Address) -> Result<(), ContractError> {
+    if owner_of(env, operator).as_ref() != Some(resolver) {
+        return Err(ContractError::Unauthorized);
+    }
+
+    let mut operators = get_operators(env, resolver);
+    if let Some(index) = operators.first_index_of(operator) {
+        operators.remove(index);
+    }
+    set_operators(env, resolver, &operators);
+    env.storage()
+        .persistent()
+        .remove(&StorageKey::OperatorOf(operator.clone()));
+    Ok(())
+}
+
+pub fn get_profile(env: &Env, resolver: &Address) -> Option<ResolverProfile> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::ResolverProfile(resolver.clone()))
+}
+
+pub fn set_profile(env: &Env, resolver: &Address, profile: &mut ResolverProfile) -> Result<(), ContractError> {
+    get_resolver(env, resolver)?;
+
+    if profile.display_name.len() > MAX_DISPLAY_NAME_LEN
+        || profile.api_uri.len() > MAX_API_URI_LEN
+        || profile.supported_tokens.len() > MAX_SUPPORTED_TOKENS
+        || profile.chain_ids.len() > MAX_CHAIN_IDS
+    {
+        return Err(ContractError::LimitExceeded);
+    }
+    if profile.max_fill_size.is_some_and(|size| size <= 0) {
+        return Err(ContractError::InvalidAmount);
+    }
+
+    profile.updated_at = env.ledger().timestamp();
+    env.storage()
+        .persistent()
+        .set(&StorageKey::ResolverProfile(resolver.clone()), profile);
+    Ok(())
+}
+
+pub fn is_unbonding(data: &Resolver) -> bool {
+    data.unbonding_amount > 0
+}
+
+// Whether the resolver may take new fills; an unbonding resolver is out of
+// rotation until it withdraws
+pub fn check_can_fill(data: &Resolver) -> Result<(), ContractError> {
+    if !data.active || is_unbonding(data) {
+        return Err(ContractError::ResolverNotActive);
+    }
+    Ok(())
+}
+
+// Effective stake below the minimum or a jailing takes the resolver out of rotation
+pub fn sync_active(data: &mut Resolver, min_stake: i128) {
+    data.active = !jailing::is_jailed(data) && delegation::effective_stake(data) >= min_stake;
+}
+
+pub fn increase_stake(env: &Env, resolver: &Address, amount: i128) -> Result<Resolver, ContractError> {
+    if amount <= 0 {
+        return Err(ContractError::InvalidAmount);
+    }
+
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut data = get_resolver(env, resolver)?;
+
+    let token_client = token::Client::new(env, &config.native_token);
+    token_client.transfer(resolver, &env.current_contract_address(), &amount);
+
+    data.stake += amount;
+    sync_active(&mut data, config.min_stake);
+    set_resolver(env, resolver, &data);
+
+    Ok(data)
+}
+
+// Move stake into unbonding; repeated requests add to the amount and restart the period
+pub fn request_unstake(env: &Env, resolver: &Address, amount: i128) -> Result<Resolver, ContractError> {
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut data = get_resolver(env, resolver)?;
+
+    if amount <= 0 {
+        return Err(ContractError::InvalidAmount);
+    }
+    if amount > data.stake {
+        return Err(ContractError::InsufficientStake);
+    }
+
+    data.stake -= amount;
+    data.unbonding_amount += amount;
+    data.unbonding_until = env.ledger().timestamp() + config.unbonding_period;
+    sync_active(&mut data, config.min_stake);
+    set_resolver(env, resolver, &data);
+
+    Ok(data)
+}
+
+pub fn withdraw_stake(env: &Env, resolver: &Address) -> Result<i128, ContractError> {
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut data = get_resolver(env, resolver)?;
+
+    if !is_unbonding(&data) {
+        return Err(ContractError::NoUnbondingStake);
+    }
+    if env.ledger().timestamp() < data.unbonding_until {
+        return Err(ContractError::UnbondingNotComplete);
+    }
+
+    let amount = data.unbonding_amount;
+    data.unbonding_amount = 0;
+    data.unbonding_until = 0;
+    set_resolver(env, resolver, &data);
+
+    let token_client = token::Client::new(env, &config.native_token);
+    token_client.transfer(&env.current_contract_address(), resolver, &amount);
+
+    Ok(amount)
+}
@@ -0,0 +1,38 @@
+use soroban_sdk::{token, Address, Env};
+use crate::error::ContractError;
+use crate::StorageKey;
+
+// Settled fill slices and fill rewards accrue here and are pulled with
+// claim_rewards, so neither a fill nor a completion depends on a transfer to
+// the resolver succeeding
+pub fn get_rewards(env: &Env, resolver: &Address, token: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::Rewards(resolver.clone(), token.clone()))
+        .unwrap_or(0)
+}
+
+pub fn accrue(env: &Env, resolver: &Address, token: &Address, amount: i128) {
+    if amount <= 0 {
+        return;
+    }
+    let pending = get_rewards(env, resolver, token);
+    env.storage()
+        .persistent()
+        .set(&StorageKey::Rewards(resolver.clone(), token.clone()), &(pending + amount));
+}
+
+pub fn claim(env: &Env, resolver: &Address, token: &Address) -> Result<i128, ContractError> {
+    let amount = get_rewards(env, resolver, token);
+    if amount == 0 {
+        return Err(ContractError::InsufficientBalance);
+    }
+    env.storage()
+        .persistent()
+        .remove(&StorageKey::Rewards(resolver.clone(), token.clone()));
+
+    let token_client = token::Client::new(env, token);
+    token_client.transfer(&env.current_contract_address(), resolver, &amount);
+
+    Ok(amount)
+}
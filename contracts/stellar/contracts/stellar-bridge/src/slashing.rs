@@ -0,0 +1,151 @@
+use soroban_sdk::{token, Address, BytesN, Env, Symbol, Vec};
+use crate::delegation;
+use crate::error::ContractError;
+use crate::reputation::{self, Outcome};
+use crate::resolver::{get_resolver, set_resolver, sync_active};
+use crate::types::{SlashRecord, Swap};
+use crate::{Config, StorageKey};
+
+// Share of each slash kept in the insurance fund, in basis points
+pub const DEFAULT_INSURANCE_RATE: u32 = 5000;
+
+// A single slash can take at most half of the resolver's bonded stake
+pub const MAX_SLASH_RATE: i128 = 5000;
+
+// Oldest records are dropped past this length
+pub const MAX_SLASH_HISTORY: u32 = 50;
+
+pub fn is_arbiter(env: &Env, address: &Address) -> bool {
+    env.storage()
+        .instance()
+        .get(&StorageKey::Arbiter(address.clone()))
+        .unwrap_or(false)
+}
+
+pub fn get_insurance_fund(env: &Env) -> i128 {
+    env.storage().instance().get(&StorageKey::InsuranceFund).unwrap_or(0)
+}
+
+fn set_insurance_fund(env: &Env, amount: i128) {
+    env.storage().instance().set(&StorageKey::InsuranceFund, &amount);
+}
+
+pub fn get_history(env: &Env, resolver: &Address) -> Vec<SlashRecord> {
+    env.storage()
+        .persistent()
+        .get(&StorageKey::SlashHistory(resolver.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+// Take `amount` out of everything bonded behind the resolver. Delegators lose
+// their proportional part; the resolver's part is drawn from its stake, then
+// its unbonding stake.
+pub fn slash_resolver(
+    env: &Env,
+    resolver: &Address,
+    swap_id: &BytesN<32>,
+    amount: i128,
+    reason: Symbol,
+    slashed_by: &Address,
+) -> Result<SlashRecord, ContractError> {
+    if amount <= 0 {
+        return Err(ContractError::InvalidAmount);
+    }
+
+    let swap: Swap = env
+        .storage()
+        .persistent()
+        .get(&StorageKey::Swap(swap_id.clone()))
+        .ok_or(ContractError::SwapNotFound)?;
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let mut data = get_resolver(env, resolver)?;
+
+    let own = data.stake + data.unbonding_amount;
+    let delegated = data.delegated_stake + delegation::get_pool(env, resolver).unbonding;
+    let bonded = own + delegated;
+    if amount > bonded * MAX_SLASH_RATE / 10000 {
+        return Err(ContractError::LimitExceeded);
+    }
+
+    let from_delegators = amount * delegated / bonded;
+    delegation::slash(env, resolver, &mut data, from_delegators);
+
+    let own_amount = amount - from_delegators;
+    let from_stake = own_amount.min(data.stake);
+    data.stake -= from_stake;
+    data.unbonding_amount -= own_amount - from_stake;
+    if data.unbonding_amount == 0 {
+        data.unbonding_until = 0;
+    }
+    sync_active(&mut data, config.min_stake);
+    reputation::apply(env, resolver, &mut data, Outcome::Slashed);
+    set_resolver(env, resolver, &data);
+
+    Ok(distribute(env, resolver, swap_id, &swap.initiator, amount, reason, slashed_by))
+}
+
+// Split slashed stake between the affected initiator and the insurance fund
+// and record it against the resolver. The stake must already be deducted.
+pub fn distribute(
+    env: &Env,
+    resolver: &Address,
+    swap_id: &BytesN<32>,
+    initiator: &Address,
+    amount: i128,
+    reason: Symbol,
+    slashed_by: &Address,
+) -> SlashRecord {
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+
+    let to_insurance = amount * config.insurance_rate as i128 / 10000;
+    let to_initiator = amount - to_insurance;
+
+    if to_initiator > 0 {
+        let token_client = token::Client::new(env, &config.native_token);
+        token_client.transfer(&env.current_contract_address(), initiator, &to_initiator);
+    }
+    set_insurance_fund(env, get_insurance_fund(env) + to_insurance);
+
+    let record = SlashRecord {
+        swap_id: swap_id.clone(),
+        amount,
+        to_initiator,
+        to_insurance,
+        reason: reason.clone(),
+        slashed_by: slashed_by.clone(),
+        timestamp: env.ledger().timestamp(),
+    };
+
+    let mut history = get_history(env, resolver);
+    if history.len() >= MAX_SLASH_HISTORY {
+        history.pop_front();
+    }
+    history.push_back(record.clone());
+    env.storage()
+        .persistent()
+        .set(&StorageKey::SlashHistory(resolver.clone()), &history);
+
+    env.events().publish(
+        (Symbol::new(env, "resolver_slashed"), resolver.clone()),
+        (swap_id.clone(), amount, to_initiator, to_insurance, reason),
+    );
+
+    record
+}
+
+pub fn withdraw_insurance(env: &Env, to: &Address, amount: i128) -> Result<(), ContractError> {
+    if amount <= 0 {
+        return Err(ContractError::InvalidAmount);
+    }
+    let fund = get_insurance_fund(env);
+    if amount > fund {
+        return Err(ContractError::InsufficientBalance);
+    }
+    set_insurance_fund(env, fund - amount);
+
+    let config: Config = env.storage().instance().get(&StorageKey::Config).unwrap();
+    let token_client = token::Client::new(env, &config.native_token);
+    token_client.transfer(&env.current_contract_address(), to, &amount);
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use soroban_sdk::{contracttype, Address, Bytes, BytesN};
+use crate::partial_fills::MERKLE_TREE_LEGACY;
 
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -11,13 +12,114 @@ pub struct Swap {
     pub timelock: u64,
     pub ethereum_address: Bytes,
     pub state: SwapState,
-    pub partial_fill_enabled: bool,
+    pub fill_mode: FillMode,
     pub merkle_root: BytesN<32>,
+    // Leaf hashing scheme of merkle_root, see partial_fills::MERKLE_TREE_VERSION
+    pub merkle_version: u32,
     pub created_at: u64,
+    // Number of fill segments; 0 means a single secret_hash unlocks the swap,
+    // otherwise merkle_root commits secret_parts + 1 indexed secret hashes
+    pub secret_parts: u32,
+    pub last_secret_index: u32,
+    // Principal already paid out of escrow (withdrawn segments)
+    pub released: i128,
+    // Maker fee escrowed on top of amount that funds fill rewards; shrinks as
+    // fills take their reward
+    pub fee: i128,
+    pub min_fill_amount: i128,
+    pub fill_step: Option<i128>,
+    // Resolvers holding the order exclusively until exclusivity_deadline;
+    // empty for orders open to every active resolver
+    pub exclusive_resolvers: soroban_sdk::Vec<Address>,
+    pub exclusivity_deadline: u64,
 }
 
+// Maker-defined fill rules supplied to initiate_swap
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FillTerms {
+    pub fill_mode: FillMode,
+    pub secret_parts: u32,
+    pub min_fill_amount: i128,
+    pub fill_step: Option<i128>,
+    // RFQ counterparties and the ledger timestamp their exclusivity ends;
+    // an empty set with a zero deadline opens the order to everyone
+    pub exclusive_resolvers: soroban_sdk::Vec<Address>,
+    pub exclusivity_deadline: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FillMode {
+    // A single fill for the full amount
+    FillOrKill,
+    AllowPartial,
+    // Partial fills, capped at the given number of fills
+    AllowMultipleFills(u32),
+    // Partial fills; the unfilled remainder is refundable once the first fill lands
+    ImmediateOrCancel,
+}
+
+// Legacy partial_fill_enabled flags map onto the equivalent modes
+impl From<bool> for FillMode {
+    fn from(partial_fill_enabled: bool) -> Self {
+        if partial_fill_enabled {
+            FillMode::AllowPartial
+        } else {
+            FillMode::FillOrKill
+        }
+    }
+}
+
+// Swap layout stored before fill modes were introduced; fields added since
+// then take their defaults on migration
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacySwap {
+    pub initiator: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub filled: i128,
+    pub secret_hash: BytesN<32>,
+    pub timelock: u64,
+    pub ethereum_address: Bytes,
+    pub state: SwapState,
+    pub partial_fill_enabled: bool,
+    pub merkle_root: BytesN<32>,
+    pub created_at: u64,
+}
+
+impl From<LegacySwap> for Swap {
+    fn from(legacy: LegacySwap) -> Self {
+        let exclusive_resolvers = soroban_sdk::Vec::new(legacy.ethereum_address.env());
+        Swap {
+            initiator: legacy.initiator,
+            token: legacy.token,
+            amount: legacy.amount,
+            filled: legacy.filled,
+            secret_hash: legacy.secret_hash,
+            timelock: legacy.timelock,
+            ethereum_address: legacy.ethereum_address,
+            state: legacy.state,
+            fill_mode: legacy.partial_fill_enabled.into(),
+            merkle_root: legacy.merkle_root,
+            merkle_version: MERKLE_TREE_LEGACY,
+            created_at: legacy.created_at,
+            secret_parts: 0,
+            last_secret_index: 0,
+            released: 0,
+            // Legacy swaps escrowed no maker fee, so their fills earn no reward
+            fee: 0,
+            min_fill_amount: 0,
+            fill_step: None,
+            exclusive_resolvers,
+            exclusivity_deadline: 0,
+        }
+    }
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum SwapState {
     Initiated = 0,
     PartialFilled = 1,
@@ -35,6 +137,130 @@ pub struct Resolver {
     pub success_rate: u32,
     pub active: bool,
     pub registration_time: u64,
+    // Stake waiting out the unbonding period; unbonding resolvers can't fill
+    pub unbonding_amount: i128,
+    pub unbonding_until: u64,
+    // Time reputation was last scored; decay runs from here, see reputation.rs
+    pub last_outcome_at: u64,
+    // Third-party stake bonded behind the resolver, see delegation.rs
+    pub delegated_stake: i128,
+    // Failures in the current jailing window, see jailing.rs
+    pub recent_failures: u32,
+    pub failure_window_start: u64,
+    // Nonzero while jailed; the resolver must unjail once it passes
+    pub jailed_until: u64,
+    pub jail_reason: soroban_sdk::Symbol,
+}
+
+// Delegators' side of a resolver's stake, tracked in shares
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DelegationPool {
+    // Shares of Resolver.delegated_stake
+    pub shares: i128,
+    // Delegated stake waiting out the delegator unbonding period
+    pub unbonding: i128,
+    pub unbonding_shares: i128,
+    // Resolver's cut of the delegators' reward share, in basis points
+    pub commission_rate: u32,
+    // Raised rate taking effect at commission_effective_at (0 when none is scheduled)
+    pub pending_commission_rate: u32,
+    pub commission_effective_at: u64,
+    // Cumulative delegator reward per share, per reward token
+    pub reward_index: soroban_sdk::Map<Address, i128>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Delegation {
+    pub shares: i128,
+    pub unbonding_shares: i128,
+    pub unbonding_until: u64,
+    // Pool reward index per token as of the last settlement
+    pub reward_checkpoints: soroban_sdk::Map<Address, i128>,
+}
+
+// A delegation valued in native_token
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DelegationInfo {
+    pub amount: i128,
+    pub unbonding_amount: i128,
+    pub unbonding_until: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SlashRecord {
+    pub swap_id: BytesN<32>,
+    pub amount: i128,
+    pub to_initiator: i128,
+    pub to_insurance: i128,
+    pub reason: soroban_sdk::Symbol,
+    // Admin or arbiter, or the contract itself for lapsed reservations
+    pub slashed_by: Address,
+    pub timestamp: u64,
+}
+
+// Self-declared resolver metadata for takers and frontends
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResolverProfile {
+    pub display_name: soroban_sdk::String,
+    // Off-chain endpoint quotes and fill intents are sent to
+    pub api_uri: soroban_sdk::String,
+    pub supported_tokens: soroban_sdk::Vec<Address>,
+    pub chain_ids: soroban_sdk::Vec<u64>,
+    // None when the resolver takes fills of any size
+    pub max_fill_size: Option<i128>,
+    pub updated_at: u64,
+}
+
+// Ranking used by get_top_resolvers
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ResolverSort {
+    Reputation,
+    Volume,
+    Stake,
+}
+
+// How plan_fills weights resolvers against each other
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FillWeighting {
+    Stake,
+    Reputation,
+    // Declared per-resolver capacity, in the same order as the resolvers;
+    // also caps each resolver's slice
+    Capacity(soroban_sdk::Vec<i128>),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FillSlice {
+    pub resolver: Address,
+    pub amount: i128,
+}
+
+// Exclusive claim on a slice of a swap until expires_at
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FillReservation {
+    pub amount: i128,
+    // Stake set aside, slashed if the reservation lapses
+    pub bond: i128,
+    // fill_leaf of the fill the resolver will reveal
+    pub commitment: BytesN<32>,
+    pub expires_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SwapReservations {
+    pub reservations: soroban_sdk::Map<Address, FillReservation>,
+    pub reserved: i128,
+    pub available: i128,
 }
 
 #[contracttype]
@@ -44,6 +270,50 @@ pub struct PartialFill {
     pub amount: i128,
     pub timestamp: u64,
     pub merkle_proof: soroban_sdk::Vec<BytesN<32>>,
+    // Auction rate the fill was accepted at, 0 outside an auction
+    pub rate: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Auction {
+    pub start_rate: i128,
+    pub end_rate: i128,
+    pub start_time: u64,
+    pub duration: u64,
+    pub points: soroban_sdk::Vec<AuctionPoint>,
+    pub gas_bump: GasBump,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuctionPoint {
+    pub time_offset: u64,
+    pub rate: i128,
+}
+
+// Rate reduction of gas_bump_estimate at a reported fee of gas_price_estimate,
+// a zero estimate disables the bump
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GasBump {
+    pub gas_bump_estimate: i128,
+    pub gas_price_estimate: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NetworkFee {
+    pub fee: i128,
+    pub updated_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FillQuote {
+    pub rate: i128,
+    pub taking_amount: i128,
+    pub reward: i128,
 }
 
 #[contracttype]
@@ -54,4 +324,22 @@ pub struct Analytics {
     pub total_resolvers: u32,
     pub success_rate: u32,
     pub average_completion_time: u64,
+    pub completed_swaps: u32,
+    pub refunded_swaps: u32,
+    pub expired_swaps: u32,
+    // Sum of now - created_at over completed swaps
+    pub total_completion_time: u64,
+}
+
+// Activity in a single token, all-time or for one day; amounts in that token
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenAnalytics {
+    pub volume: i128,
+    pub swaps: u32,
+    pub fills: u32,
+    // Refunded and expired swaps
+    pub refunds: u32,
+    // Fill rewards paid out of escrow
+    pub fees: i128,
 }
@@ -1,19 +1,135 @@
 use soroban_sdk::{
-    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation},
-    Address, Env, BytesN, Bytes,
+    testutils::{Address as _, Ledger},
+    token,
+    Address, Bytes, BytesN, Env, String, Symbol, Vec,
 };
 use stellar_bridge::{
-    StellarBridgeContract, StellarBridgeContractClient,
-    types::{Swap, SwapState, Resolver, Analytics},
+    types::{
+        AuctionPoint, FillMode, FillSlice, FillTerms, FillWeighting, GasBump, LegacySwap,
+        ResolverProfile, ResolverSort, SwapState,
+    },
+    ContractError, StellarBridge, StellarBridgeClient, StorageKey,
 };
+use stellar_bridge_merkle::{Leaf, MerkleTree, MERKLE_TREE_VERSION};
 
 #[cfg(test)]
 mod integration_tests {
     use super::*;
 
-    fn create_contract(e: &Env) -> StellarBridgeContractClient {
-        let contract_address = e.register_contract(None, StellarBridgeContract);
-        StellarBridgeContractClient::new(e, &contract_address)
+    const MIN_TIMELOCK: u64 = 60;
+    const MAX_TIMELOCK: u64 = 86_400;
+    const MIN_STAKE: i128 = 1_000_000_000;
+    const BASE_FEE_RATE: u32 = 30;
+    const RESOLVER_REWARD_RATE: u32 = 10;
+    const MAX_RESOLVERS_PAGE: u32 = 15;
+    const MAX_PLAN_RESOLVERS: u32 = 10;
+
+    struct TestSetup<'a> {
+        admin: Address,
+        token: Address,
+        native_token: Address,
+        contract: StellarBridgeClient<'a>,
+    }
+
+    fn create_contract(e: &Env) -> StellarBridgeClient<'_> {
+        let contract_address = e.register_contract(None, StellarBridge);
+        StellarBridgeClient::new(e, &contract_address)
+    }
+
+    fn setup(env: &Env) -> TestSetup<'_> {
+        let admin = Address::generate(env);
+        let token = env.register_stellar_asset_contract(admin.clone());
+        let native_token = env.register_stellar_asset_contract(admin.clone());
+
+        let contract = create_contract(env);
+        contract.initialize(
+            &admin,
+            &MIN_TIMELOCK,
+            &MAX_TIMELOCK,
+            &MIN_STAKE,
+            &BASE_FEE_RATE,
+            &RESOLVER_REWARD_RATE,
+            &native_token,
+        );
+
+        TestSetup {
+            admin,
+            token,
+            native_token,
+            contract,
+        }
+    }
+
+    fn mint(env: &Env, token: &Address, to: &Address, amount: i128) {
+        token::StellarAssetClient::new(env, token).mint(to, &amount);
+    }
+
+    // Swap amount plus the maker fee that funds fill rewards
+    fn with_fee(amount: i128) -> i128 {
+        amount + amount * RESOLVER_REWARD_RATE as i128 / 10000
+    }
+
+    fn balance(env: &Env, token: &Address, of: &Address) -> i128 {
+        token::Client::new(env, token).balance(of)
+    }
+
+    fn fill_terms(env: &Env, fill_mode: FillMode, secret_parts: u32) -> FillTerms {
+        FillTerms {
+            fill_mode,
+            secret_parts,
+            min_fill_amount: 0,
+            fill_step: None,
+            exclusive_resolvers: Vec::new(env),
+            exclusivity_deadline: 0,
+        }
+    }
+
+    fn register_resolver(env: &Env, t: &TestSetup, resolver: &Address, stake: i128) {
+        mint(env, &t.native_token, resolver, stake);
+        t.contract.register_resolver(resolver, &stake);
+    }
+
+    fn strkey(address: &Address) -> std::string::String {
+        let strkey = address.to_string();
+        let mut buf = std::vec![0u8; strkey.len() as usize];
+        strkey.copy_into_slice(&mut buf);
+        std::string::String::from_utf8(buf).unwrap()
+    }
+
+    // Leaves and trees come from the off-chain builder, so every test also
+    // checks that its proofs match what the contract verifies
+    fn fill_leaf(env: &Env, resolver: &Address, amount: i128, nonce: u64) -> BytesN<32> {
+        let leaf = Leaf::Fill {
+            resolver: strkey(resolver),
+            amount,
+            nonce,
+        };
+        BytesN::from_array(env, &leaf.hash().unwrap())
+    }
+
+    fn secret_leaf(env: &Env, index: u32, secret: &BytesN<32>) -> BytesN<32> {
+        let leaf = Leaf::Secret {
+            index,
+            secret_hash: env.crypto().keccak256(&secret.clone().into()).to_array(),
+        };
+        BytesN::from_array(env, &leaf.hash().unwrap())
+    }
+
+    // Builds a sorted-pair merkle tree, returning the root and a proof per leaf
+    fn build_tree(env: &Env, leaves: &[BytesN<32>]) -> (BytesN<32>, std::vec::Vec<Vec<BytesN<32>>>) {
+        let hashes = leaves.iter().map(|leaf| leaf.to_array()).collect();
+        let tree = MerkleTree::from_leaf_hashes(MERKLE_TREE_VERSION, hashes).unwrap();
+
+        let proofs = (0..leaves.len())
+            .map(|i| {
+                let mut proof = Vec::new(env);
+                for element in tree.proof(i).unwrap() {
+                    proof.push_back(BytesN::from_array(env, &element));
+                }
+                proof
+            })
+            .collect();
+        (BytesN::from_array(env, &tree.root()), proofs)
     }
 
     #[test]
@@ -21,450 +137,2157 @@ mod integration_tests {
         let env = Env::default();
         env.mock_all_auths();
 
-        let admin = Address::generate(&env);
+        let t = setup(&env);
         let initiator = Address::generate(&env);
-        let token = env.register_stellar_asset_contract(admin.clone());
-        
-        let contract = create_contract(&env);
-        
-        // Initialize contract
-        contract.initialize(&admin);
-        
+        let resolver = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, MIN_STAKE);
+
         // Setup test parameters
-        let amount = 1_500_000_000i128; // 15,000 XLM in stroops
+        let amount = 1_500_000_000i128; // 150 XLM in stroops
         let secret = BytesN::from_array(&env, &[1; 32]);
-        let secret_hash = env.crypto().keccak256(&secret);
+        let secret_hash = env.crypto().keccak256(&secret.clone().into());
         let timelock = env.ledger().timestamp() + 3600;
         let ethereum_address = Bytes::from_slice(&env, &[0u8; 20]);
         let merkle_root = BytesN::from_array(&env, &[2; 32]);
-        
+
         // Mint tokens to initiator
-        env.as_contract(&token, || {
-            env.invoke_contract(
-                &token,
-                &"mint",
-                (initiator.clone(), amount).into_val(&env)
-            )
-        });
-        
+        mint(&env, &t.token, &initiator, with_fee(amount));
+
         // Initiate swap
         let swap_id = contract.initiate_swap(
             &initiator,
-            &token,
+            &t.token,
             &amount,
             &secret_hash,
             &timelock,
             &ethereum_address,
-            &true, // partial fills enabled
-            &merkle_root
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 0),
         );
-        
+
         // Verify swap creation
-        let swap = contract.get_swap(&swap_id).unwrap();
+        let swap = contract.get_swap(&swap_id);
         assert_eq!(swap.initiator, initiator);
         assert_eq!(swap.amount, amount);
         assert_eq!(swap.state, SwapState::Initiated);
-        
+
         // Complete swap
-        contract.complete_swap(&swap_id, &secret);
-        
+        contract.complete_swap(&swap_id, &resolver, &secret);
+
         // Verify completion
-        let completed_swap = contract.get_swap(&swap_id).unwrap();
+        let completed_swap = contract.get_swap(&swap_id);
         assert_eq!(completed_swap.state, SwapState::Completed);
+        assert_eq!(balance(&env, &t.token, &resolver), amount);
     }
-    
+
     #[test]
     fn test_partial_fills_with_multiple_resolvers() {
         let env = Env::default();
         env.mock_all_auths();
-        
-        let admin = Address::generate(&env);
+
+        let t = setup(&env);
         let initiator = Address::generate(&env);
         let resolver1 = Address::generate(&env);
         let resolver2 = Address::generate(&env);
         let resolver3 = Address::generate(&env);
-        let token = env.register_stellar_asset_contract(admin.clone());
-        
-        let contract = create_contract(&env);
-        contract.initialize(&admin);
-        
+        let contract = &t.contract;
+
         // Register resolvers
         let stake_amount = 2_000_000_000i128;
-        contract.register_resolver(&resolver1, &stake_amount);
-        contract.register_resolver(&resolver2, &stake_amount);
-        contract.register_resolver(&resolver3, &stake_amount);
-        
+        register_resolver(&env, &t, &resolver1, stake_amount);
+        register_resolver(&env, &t, &resolver2, stake_amount);
+        register_resolver(&env, &t, &resolver3, stake_amount);
+
         // Setup large swap for partial fills
-        let total_amount = 10_000_000_000i128; // 100,000 XLM
-        let secret_hash = BytesN::from_array(&env, &[3; 32]);
+        let total_amount = 10_000_000_000i128; // 1,000 XLM
+        let secret = BytesN::from_array(&env, &[3; 32]);
+        let secret_hash = env.crypto().keccak256(&secret.clone().into());
         let timelock = env.ledger().timestamp() + 7200;
         let ethereum_address = Bytes::from_slice(&env, &[1u8; 20]);
-        let merkle_root = BytesN::from_array(&env, &[4; 32]);
-        
+
+        let fill_amounts = [
+            3_333_333_333i128, // ~33.33%
+            3_333_333_333i128, // ~33.33%
+            3_333_333_334i128, // ~33.34% (remainder)
+        ];
+        let resolvers = [&resolver1, &resolver2, &resolver3];
+
+        // Commit the allowed fills in the merkle root
+        let leaves: std::vec::Vec<BytesN<32>> = resolvers
+            .iter()
+            .zip(fill_amounts.iter())
+            .enumerate()
+            .map(|(i, (r, &a))| fill_leaf(&env, r, a, (i + 1) as u64))
+            .collect();
+        let (merkle_root, merkle_proofs) = build_tree(&env, &leaves);
+
         // Mint tokens
-        env.as_contract(&token, || {
-            env.invoke_contract(
-                &token,
-                &"mint",
-                (initiator.clone(), total_amount).into_val(&env)
-            )
-        });
-        
+        mint(&env, &t.token, &initiator, with_fee(total_amount));
+
         // Initiate swap
         let swap_id = contract.initiate_swap(
             &initiator,
-            &token,
+            &t.token,
             &total_amount,
             &secret_hash,
             &timelock,
             &ethereum_address,
-            &true,
-            &merkle_root
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 0),
         );
-        
+
         // Execute partial fills
-        let fill_amounts = [
-            3_333_333_333i128, // ~33.33%
-            3_333_333_333i128, // ~33.33% 
-            3_333_333_334i128, // ~33.34% (remainder)
-        ];
-        
-        let resolvers = [&resolver1, &resolver2, &resolver3];
-        let merkle_proofs = [
-            vec![&env, BytesN::from_array(&env, &[5; 32])],
-            vec![&env, BytesN::from_array(&env, &[6; 32])],
-            vec![&env, BytesN::from_array(&env, &[7; 32])],
-        ];
-        
         for (i, (&resolver_addr, &fill_amount)) in resolvers.iter().zip(fill_amounts.iter()).enumerate() {
             contract.execute_partial_fill(
                 &swap_id,
                 resolver_addr,
                 &fill_amount,
                 &merkle_proofs[i],
-                &((i + 1) as u64)
+                &((i + 1) as u64),
             );
-            
+
             // Verify partial fill
-            let swap = contract.get_swap(&swap_id).unwrap();
+            let swap = contract.get_swap(&swap_id);
             let expected_filled = fill_amounts[0..=i].iter().sum::<i128>();
             assert_eq!(swap.filled, expected_filled);
         }
-        
+
+        // Fully filled, but the slices stay escrowed until the secret arrives
+        let filled_swap = contract.get_swap(&swap_id);
+        assert_eq!(filled_swap.state, SwapState::PartialFilled);
+        assert_eq!(filled_swap.filled, total_amount);
+
+        contract.complete_swap(&swap_id, &resolver3, &secret);
+
         // Verify final state
-        let final_swap = contract.get_swap(&swap_id).unwrap();
+        let final_swap = contract.get_swap(&swap_id);
         assert_eq!(final_swap.state, SwapState::Completed);
         assert_eq!(final_swap.filled, total_amount);
-        
+        let mut claimed = 0;
+        for (&resolver_addr, &fill_amount) in resolvers.iter().zip(fill_amounts.iter()) {
+            let reward = fill_amount * RESOLVER_REWARD_RATE as i128 / 10000;
+            assert_eq!(balance(&env, &t.token, resolver_addr), 0);
+            let claim = contract.claim_rewards(resolver_addr, &t.token);
+            assert!(claim >= fill_amount + reward);
+            assert_eq!(balance(&env, &t.token, resolver_addr), claim);
+            claimed += claim;
+        }
+        // The completer's claim includes the maker fee's rounding dust
+        assert_eq!(claimed, with_fee(total_amount));
+
         // Verify resolver performance updates
         for resolver_addr in resolvers.iter() {
-            let resolver = contract.get_resolver(resolver_addr).unwrap();
+            let resolver = contract.get_resolver(resolver_addr);
             assert!(resolver.total_volume > 0);
         }
     }
-    
+
+    // Initiates a swap with two committed fills (resolver1: 3_000, resolver2: 2_000)
+    fn setup_filled_swap(
+        env: &Env,
+        t: &TestSetup,
+        initiator: &Address,
+        resolver1: &Address,
+        resolver2: &Address,
+        secret: &BytesN<32>,
+    ) -> BytesN<32> {
+        let amount = 10_000i128;
+        let leaves = [
+            fill_leaf(env, resolver1, 3_000, 1),
+            fill_leaf(env, resolver2, 2_000, 2),
+        ];
+        let (merkle_root, proofs) = build_tree(env, &leaves);
+
+        mint(env, &t.token, initiator, with_fee(amount));
+        let swap_id = t.contract.initiate_swap(
+            initiator,
+            &t.token,
+            &amount,
+            &env.crypto().keccak256(&secret.clone().into()),
+            &3600u64,
+            &Bytes::from_slice(env, &[7u8; 20]),
+            &merkle_root,
+            &fill_terms(env, FillMode::AllowPartial, 0),
+        );
+
+        t.contract.execute_partial_fill(&swap_id, resolver1, &3_000, &proofs[0], &1u64);
+        t.contract.execute_partial_fill(&swap_id, resolver2, &2_000, &proofs[1], &2u64);
+        swap_id
+    }
+
+    #[test]
+    fn test_partial_fill_settlement_on_completion() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver1 = Address::generate(&env);
+        let resolver2 = Address::generate(&env);
+        let completer = Address::generate(&env);
+        register_resolver(&env, &t, &resolver1, MIN_STAKE);
+        register_resolver(&env, &t, &resolver2, MIN_STAKE);
+        register_resolver(&env, &t, &completer, MIN_STAKE);
+
+        let secret = BytesN::from_array(&env, &[18; 32]);
+        let swap_id = setup_filled_swap(&env, &t, &initiator, &resolver1, &resolver2, &secret);
+
+        // Slices and their rewards (10 bps of maker fee) stay escrowed until settlement
+        assert_eq!(balance(&env, &t.token, &resolver1), 0);
+        assert_eq!(t.contract.get_pending_rewards(&resolver1, &t.token), 0);
+        assert_eq!(balance(&env, &t.token, &t.contract.address), 10_010);
+        let pending = t.contract.get_pending_payouts(&swap_id);
+        assert_eq!(pending.get(resolver1.clone()), Some(3_000));
+        assert_eq!(pending.get(resolver2.clone()), Some(2_000));
+        let swap = t.contract.get_swap(&swap_id);
+        assert_eq!(swap.released, 0);
+        assert_eq!(swap.fee, 5);
+
+        // Revealing the secret pays the remainder and credits every slice
+        t.contract.complete_swap(&swap_id, &completer, &secret);
+
+        assert_eq!(balance(&env, &t.token, &resolver1), 0);
+        assert_eq!(balance(&env, &t.token, &completer), 5_000);
+        assert_eq!(balance(&env, &t.token, &initiator), 0);
+        assert_eq!(balance(&env, &t.token, &t.contract.address), 5_010);
+
+        // Slices and rewards are pulled; claiming pays them out once. The
+        // completer's reward is what is left of the fee
+        assert_eq!(t.contract.get_pending_rewards(&resolver1, &t.token), 3_003);
+        assert_eq!(t.contract.get_pending_rewards(&resolver2, &t.token), 2_002);
+        assert_eq!(t.contract.claim_rewards(&resolver1, &t.token), 3_003);
+        assert_eq!(t.contract.claim_rewards(&resolver2, &t.token), 2_002);
+        assert_eq!(t.contract.claim_rewards(&completer, &t.token), 5);
+        assert!(t.contract.try_claim_rewards(&resolver1, &t.token).is_err());
+        assert_eq!(balance(&env, &t.token, &resolver1), 3_003);
+        assert_eq!(balance(&env, &t.token, &resolver2), 2_002);
+        assert_eq!(balance(&env, &t.token, &t.contract.address), 0);
+        assert_eq!(t.contract.get_pending_payouts(&swap_id).len(), 0);
+
+        let swap = t.contract.get_swap(&swap_id);
+        assert_eq!(swap.state, SwapState::Completed);
+        assert_eq!(swap.released, swap.amount);
+    }
+
+    #[test]
+    fn test_partial_fill_settlement_on_refund() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver1 = Address::generate(&env);
+        let resolver2 = Address::generate(&env);
+        register_resolver(&env, &t, &resolver1, MIN_STAKE);
+        register_resolver(&env, &t, &resolver2, MIN_STAKE);
+
+        let secret = BytesN::from_array(&env, &[19; 32]);
+        let swap_id = setup_filled_swap(&env, &t, &initiator, &resolver1, &resolver2, &secret);
+
+        // Secret never arrives: escrowed slices go back to the initiator
+        env.ledger().with_mut(|li| li.timestamp = 3600);
+        t.contract.refund_swap(&swap_id);
+
+        // Undelivered fills earn nothing; the initiator gets the full amount
+        // and maker fee back
+        assert_eq!(balance(&env, &t.token, &initiator), with_fee(10_000));
+        assert_eq!(balance(&env, &t.token, &t.contract.address), 0);
+        assert!(t.contract.try_claim_rewards(&resolver1, &t.token).is_err());
+        assert!(t.contract.try_claim_rewards(&resolver2, &t.token).is_err());
+        assert_eq!(t.contract.get_pending_payouts(&swap_id).len(), 0);
+
+        // The revealed-too-late secret can no longer move funds
+        assert!(t.contract.try_complete_swap(&swap_id, &resolver1, &secret).is_err());
+        assert_eq!(t.contract.get_swap(&swap_id).state, SwapState::Refunded);
+    }
+
+    #[test]
+    fn test_multi_secret_settlement_on_refund() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        register_resolver(&env, &t, &resolver, MIN_STAKE);
+
+        let amount = 1_000i128;
+        let secrets: std::vec::Vec<BytesN<32>> = (0..=2u8)
+            .map(|i| BytesN::from_array(&env, &[120 + i; 32]))
+            .collect();
+        let leaves: std::vec::Vec<BytesN<32>> = secrets
+            .iter()
+            .enumerate()
+            .map(|(i, secret)| secret_leaf(&env, i as u32, secret))
+            .collect();
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = t.contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[20; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[8u8; 20]),
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 2),
+        );
+        t.contract.withdraw_fill(&swap_id, &resolver, &400, &secrets[0], &0, &proofs[0]);
+
+        env.ledger().with_mut(|li| li.timestamp = 3600);
+        t.contract.refund_swap(&swap_id);
+
+        // The unspent maker fee is refunded with the unfilled segments
+        assert_eq!(balance(&env, &t.token, &resolver), 400);
+        assert_eq!(balance(&env, &t.token, &initiator), 601);
+        assert_eq!(balance(&env, &t.token, &t.contract.address), 0);
+    }
+
+    #[test]
+    fn test_fill_modes() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        let completer = Address::generate(&env);
+        register_resolver(&env, &t, &resolver, MIN_STAKE);
+        register_resolver(&env, &t, &completer, MIN_STAKE);
+
+        let amount = 10_000i128;
+        let leaves = [
+            fill_leaf(&env, &resolver, 3_000, 1),
+            fill_leaf(&env, &resolver, 2_000, 2),
+            fill_leaf(&env, &resolver, amount, 3),
+        ];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        let secret = BytesN::from_array(&env, &[24; 32]);
+        let initiate = |mode: FillMode, tag: u8| {
+            mint(&env, &t.token, &initiator, with_fee(amount));
+            t.contract.initiate_swap(
+                &initiator,
+                &t.token,
+                &amount,
+                &env.crypto().keccak256(&secret.clone().into()),
+                &(3600u64 + tag as u64),
+                &Bytes::from_slice(&env, &[tag; 20]),
+                &merkle_root,
+                &fill_terms(&env, mode, 0),
+            )
+        };
+
+        // Fill-or-kill takes a single fill for the full amount
+        let fok = initiate(FillMode::FillOrKill, 1);
+        assert!(t
+            .contract
+            .try_execute_partial_fill(&fok, &resolver, &3_000, &proofs[0], &1u64)
+            .is_err());
+        t.contract.execute_partial_fill(&fok, &resolver, &amount, &proofs[2], &3u64);
+        assert_eq!(t.contract.get_swap(&fok).filled, amount);
+
+        // Multiple fills are capped by count
+        let capped = initiate(FillMode::AllowMultipleFills(1), 2);
+        t.contract.execute_partial_fill(&capped, &resolver, &3_000, &proofs[0], &1u64);
+        assert!(t
+            .contract
+            .try_execute_partial_fill(&capped, &resolver, &2_000, &proofs[1], &2u64)
+            .is_err());
+
+        // Multi-secret swaps cannot cancel their remainder
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        assert!(t
+            .contract
+            .try_initiate_swap(
+                &initiator,
+                &t.token,
+                &amount,
+                &BytesN::from_array(&env, &[25; 32]),
+                &3600u64,
+                &Bytes::from_slice(&env, &[3u8; 20]),
+                &merkle_root,
+                &fill_terms(&env, FillMode::ImmediateOrCancel, 2),
+            )
+            .is_err());
+
+        // Immediate-or-cancel remainders are refundable after the first fill
+        let ioc = initiate(FillMode::ImmediateOrCancel, 4);
+        assert!(t.contract.try_refund_swap(&ioc).is_err());
+        t.contract.execute_partial_fill(&ioc, &resolver, &3_000, &proofs[0], &1u64);
+
+        let initiator_before = balance(&env, &t.token, &initiator);
+        t.contract.refund_swap(&ioc);
+        // The unspent maker fee comes back with the remainder
+        assert_eq!(balance(&env, &t.token, &initiator), initiator_before + 7_007);
+        assert_eq!(t.contract.get_swap(&ioc).fee, 0);
+        let swap = t.contract.get_swap(&ioc);
+        assert_eq!(swap.amount, 3_000);
+        assert_eq!(swap.state, SwapState::PartialFilled);
+        assert!(t.contract.try_refund_swap(&ioc).is_err());
+
+        // The filled slice still settles on completion
+        let pending_before = t.contract.get_pending_rewards(&resolver, &t.token);
+        t.contract.complete_swap(&ioc, &completer, &secret);
+        assert_eq!(t.contract.get_pending_rewards(&resolver, &t.token), pending_before + 3_003);
+        assert_eq!(balance(&env, &t.token, &completer), 0);
+    }
+
+    #[test]
+    fn test_migrate_legacy_swap() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let swap_id = BytesN::from_array(&env, &[26; 32]);
+        let legacy = |partial_fill_enabled: bool| LegacySwap {
+            initiator: Address::generate(&env),
+            token: t.token.clone(),
+            amount: 1_000,
+            filled: 0,
+            secret_hash: BytesN::from_array(&env, &[27; 32]),
+            timelock: 3_600,
+            ethereum_address: Bytes::new(&env),
+            state: SwapState::Initiated,
+            partial_fill_enabled,
+            merkle_root: BytesN::from_array(&env, &[0; 32]),
+            created_at: 0,
+        };
+
+        for (enabled, mode) in [(true, FillMode::AllowPartial), (false, FillMode::FillOrKill)] {
+            env.as_contract(&t.contract.address, || {
+                env.storage()
+                    .persistent()
+                    .set(&StorageKey::Swap(swap_id.clone()), &legacy(enabled));
+            });
+            t.contract.migrate_swap(&swap_id);
+            let swap = t.contract.get_swap(&swap_id);
+            assert_eq!(swap.fill_mode, mode);
+            assert_eq!(swap.merkle_version, 0);
+            assert_eq!(swap.secret_parts, 0);
+            assert_eq!(swap.released, 0);
+            assert_eq!(swap.min_fill_amount, 0);
+            assert_eq!(swap.fill_step, None);
+            assert_eq!(swap.exclusive_resolvers.len(), 0);
+            assert_eq!(swap.exclusivity_deadline, 0);
+
+            // Already migrated
+            assert!(t.contract.try_migrate_swap(&swap_id).is_err());
+        }
+    }
+
+    #[test]
+    fn test_fill_reservations() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver1 = Address::generate(&env);
+        let resolver2 = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver1, MIN_STAKE);
+        register_resolver(&env, &t, &resolver2, MIN_STAKE);
+
+        let amount = 10_000i128;
+        let leaves = [
+            fill_leaf(&env, &resolver1, 4_000, 1),
+            fill_leaf(&env, &resolver2, 7_000, 2),
+            fill_leaf(&env, &resolver2, 6_000, 3),
+        ];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[28; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[11u8; 20]),
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 0),
+        );
+
+        // Only fills committed to in the swap's tree can be reserved
+        assert_eq!(
+            contract.try_reserve_fill(&swap_id, &resolver1, &4_000, &9u64, &proofs[0]),
+            Err(Ok(ContractError::InvalidMerkleProof))
+        );
+        assert_eq!(
+            contract.try_reserve_fill(&swap_id, &resolver1, &1_000, &1u64, &proofs[0]),
+            Err(Ok(ContractError::InvalidMerkleProof))
+        );
+
+        // Reserving bonds 1% of the minimum stake, prorated by the reserved share
+        contract.reserve_fill(&swap_id, &resolver1, &4_000, &1u64, &proofs[0]);
+        assert_eq!(contract.get_resolver(&resolver1).stake, MIN_STAKE - MIN_STAKE / 250);
+        assert_eq!(
+            contract.try_reserve_fill(&swap_id, &resolver1, &4_000, &1u64, &proofs[0]),
+            Err(Ok(ContractError::ReservationExists))
+        );
+
+        let view = contract.get_reservations(&swap_id);
+        assert_eq!(view.reserved, 4_000);
+        assert_eq!(view.available, 6_000);
+        assert_eq!(view.reservations.get(resolver1.clone()).unwrap().expires_at, 1_300);
+
+        // Other resolvers are held to the unreserved capacity
+        assert_eq!(
+            contract.try_reserve_fill(&swap_id, &resolver2, &7_000, &2u64, &proofs[1]),
+            Err(Ok(ContractError::InsufficientCapacity))
+        );
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver2, &7_000, &proofs[1], &2u64)
+            .is_err());
+
+        // Revealing the committed fill releases the bond
+        contract.execute_partial_fill(&swap_id, &resolver1, &4_000, &proofs[0], &1u64);
+        assert_eq!(contract.get_resolver(&resolver1).stake, MIN_STAKE);
+        assert_eq!(contract.get_reservations(&swap_id).reservations.len(), 0);
+
+        // A fill that has already been revealed can't be reserved again
+        assert_eq!(
+            contract.try_reserve_fill(&swap_id, &resolver1, &4_000, &1u64, &proofs[0]),
+            Err(Ok(ContractError::FillNonceAlreadyUsed))
+        );
+
+        // A lapsed reservation is slashed to the initiator and the insurance fund
+        let bond = 6 * MIN_STAKE / 1_000;
+        contract.reserve_fill(&swap_id, &resolver2, &6_000, &3u64, &proofs[2]);
+        assert_eq!(contract.get_reservations(&swap_id).available, 0);
+        env.ledger().with_mut(|li| li.timestamp = 1_300);
+        assert_eq!(contract.get_reservations(&swap_id).available, 6_000);
+        assert_eq!(contract.expire_reservations(&swap_id), bond);
+        assert_eq!(balance(&env, &t.native_token, &initiator), bond / 2);
+        assert_eq!(contract.get_insurance_fund(), bond / 2);
+        assert_eq!(contract.get_resolver(&resolver2).stake, MIN_STAKE - bond);
+        assert_eq!(contract.get_resolver(&resolver2).reputation, 950);
+        assert_eq!(contract.get_resolver(&resolver2).success_rate, 9_000);
+        assert_eq!(contract.get_reservations(&swap_id).reservations.len(), 0);
+
+        let history = contract.get_slash_history(&resolver2);
+        assert_eq!(history.len(), 1);
+        let record = history.get(0).unwrap();
+        assert_eq!(record.amount, bond);
+        assert_eq!(record.reason, Symbol::new(&env, "reservation_expired"));
+        assert_eq!(record.slashed_by, contract.address);
+    }
+
+    #[test]
+    fn test_plan_fills() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver1 = Address::generate(&env);
+        let resolver2 = Address::generate(&env);
+        let unregistered = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver1, MIN_STAKE);
+        register_resolver(&env, &t, &resolver2, 2 * MIN_STAKE);
+
+        let amount = 9_000i128;
+        let terms = FillTerms {
+            min_fill_amount: 1_000,
+            ..fill_terms(&env, FillMode::AllowPartial, 0)
+        };
+        let leaves = [fill_leaf(&env, &resolver1, 3_000, 1)];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[29; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[11u8; 20]),
+            &merkle_root,
+            &terms,
+        );
+        let resolvers = Vec::from_array(&env, [resolver1.clone(), resolver2.clone(), unregistered]);
+        let slice = |resolver: &Address, amount: i128| FillSlice {
+            resolver: resolver.clone(),
+            amount,
+        };
+
+        // Weighted by stake; unknown resolvers are skipped
+        let plan = contract.plan_fills(&swap_id, &resolvers, &FillWeighting::Stake);
+        assert_eq!(plan, Vec::from_array(&env, [slice(&resolver1, 3_000), slice(&resolver2, 6_000)]));
+
+        // Declared capacities cap each slice
+        let capacities = Vec::from_array(&env, [1_000, 2_000, 5_000]);
+        let plan = contract.plan_fills(&swap_id, &resolvers, &FillWeighting::Capacity(capacities));
+        assert_eq!(plan, Vec::from_array(&env, [slice(&resolver1, 1_000), slice(&resolver2, 2_000)]));
+
+        // Only the unreserved remainder is planned
+        contract.reserve_fill(&swap_id, &resolver1, &3_000, &1u64, &proofs[0]);
+        let plan = contract.plan_fills(&swap_id, &resolvers, &FillWeighting::Reputation);
+        assert_eq!(plan, Vec::from_array(&env, [slice(&resolver1, 3_000), slice(&resolver2, 3_000)]));
+
+        // A single plan weighs at most MAX_PLAN_RESOLVERS resolvers
+        let mut crowd = Vec::new(&env);
+        for _ in 0..=MAX_PLAN_RESOLVERS {
+            crowd.push_back(Address::generate(&env));
+        }
+        assert_eq!(
+            contract.try_plan_fills(&swap_id, &crowd, &FillWeighting::Stake),
+            Err(Ok(ContractError::LimitExceeded))
+        );
+    }
+
     #[test]
     fn test_swap_refund_after_timeout() {
         let env = Env::default();
         env.mock_all_auths();
-        
-        let admin = Address::generate(&env);
+
+        let t = setup(&env);
         let initiator = Address::generate(&env);
-        let token = env.register_stellar_asset_contract(admin.clone());
-        
-        let contract = create_contract(&env);
-        contract.initialize(&admin);
-        
+        let contract = &t.contract;
+
         let amount = 5_000_000_000i128;
         let secret_hash = BytesN::from_array(&env, &[8; 32]);
         let short_timelock = env.ledger().timestamp() + 60; // 1 minute
         let ethereum_address = Bytes::from_slice(&env, &[2u8; 20]);
         let merkle_root = BytesN::from_array(&env, &[9; 32]);
-        
+
         // Mint and initiate swap
-        env.as_contract(&token, || {
-            env.invoke_contract(
-                &token,
-                &"mint",
-                (initiator.clone(), amount).into_val(&env)
-            )
-        });
-        
+        mint(&env, &t.token, &initiator, with_fee(amount));
+
         let swap_id = contract.initiate_swap(
             &initiator,
-            &token,
+            &t.token,
             &amount,
             &secret_hash,
             &short_timelock,
             &ethereum_address,
-            &false,
-            &merkle_root
+            &merkle_root,
+            &fill_terms(&env, FillMode::FillOrKill, 0),
         );
-        
+
         // Advance time past timelock
         env.ledger().with_mut(|li| {
             li.timestamp = short_timelock + 1;
         });
-        
+
         // Refund should now be possible
         contract.refund_swap(&swap_id);
-        
+
         // Verify refund
-        let refunded_swap = contract.get_swap(&swap_id).unwrap();
+        let refunded_swap = contract.get_swap(&swap_id);
         assert_eq!(refunded_swap.state, SwapState::Refunded);
-        
-        // Verify tokens returned to initiator
-        let initiator_balance: i128 = env.as_contract(&token, || {
-            env.invoke_contract(
-                &token,
-                &"balance",
-                (initiator.clone(),).into_val(&env)
-            )
-        });
-        assert_eq!(initiator_balance, amount);
+
+        // Verify tokens and the maker fee returned to initiator
+        assert_eq!(balance(&env, &t.token, &initiator), with_fee(amount));
     }
-    
+
     #[test]
     fn test_resolver_registration_and_performance() {
         let env = Env::default();
         env.mock_all_auths();
-        
-        let admin = Address::generate(&env);
+
+        let t = setup(&env);
         let resolver = Address::generate(&env);
-        
-        let contract = create_contract(&env);
-        contract.initialize(&admin);
-        
-        let stake = 3_000_000_000i128; // 30 XLM
-        
+        let contract = &t.contract;
+
+        let stake = 3_000_000_000i128; // 300 XLM
+
         // Register resolver
-        contract.register_resolver(&resolver, &stake);
-        
+        register_resolver(&env, &t, &resolver, stake);
+
         // Verify registration
-        let resolver_info = contract.get_resolver(&resolver).unwrap();
+        let resolver_info = contract.get_resolver(&resolver);
         assert_eq!(resolver_info.stake, stake);
         assert_eq!(resolver_info.reputation, 1000);
         assert_eq!(resolver_info.total_volume, 0);
         assert_eq!(resolver_info.success_rate, 10000);
-        assert_eq!(resolver_info.active, true);
-        
+        assert!(resolver_info.active);
+
         // Verify analytics updated
         let analytics = contract.get_analytics();
         assert_eq!(analytics.total_resolvers, 1);
     }
-    
+
+    #[test]
+    fn test_resolver_stake_lifecycle() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, 2 * MIN_STAKE);
+
+        let amount = 10_000i128;
+        let leaves = [fill_leaf(&env, &resolver, 4_000, 1)];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        let new_swap = || {
+            mint(&env, &t.token, &initiator, with_fee(amount));
+            contract.initiate_swap(
+                &initiator,
+                &t.token,
+                &amount,
+                &BytesN::from_array(&env, &[32; 32]),
+                &3600u64,
+                &Bytes::from_slice(&env, &[11u8; 20]),
+                &merkle_root,
+                &fill_terms(&env, FillMode::AllowPartial, 0),
+            )
+        };
+        let swap_id = new_swap();
+
+        // Top-up
+        mint(&env, &t.native_token, &resolver, MIN_STAKE);
+        contract.increase_stake(&resolver, &MIN_STAKE);
+        assert_eq!(contract.get_resolver(&resolver).stake, 3 * MIN_STAKE);
+        assert!(contract.try_request_unstake(&resolver, &(4 * MIN_STAKE)).is_err());
+
+        // Unbonding below the minimum deactivates the resolver
+        contract.request_unstake(&resolver, &(5 * MIN_STAKE / 2));
+        let info = contract.get_resolver(&resolver);
+        assert_eq!(info.stake, MIN_STAKE / 2);
+        assert_eq!(info.unbonding_amount, 5 * MIN_STAKE / 2);
+        assert_eq!(info.unbonding_until, 1_000 + 7 * 24 * 60 * 60);
+        assert!(!info.active);
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver, &4_000, &proofs[0], &1u64)
+            .is_err());
+
+        // Withdrawal waits out the unbonding period
+        assert!(contract.try_withdraw_stake(&resolver).is_err());
+        env.ledger().with_mut(|li| li.timestamp = 1_000 + 7 * 24 * 60 * 60);
+        assert_eq!(contract.withdraw_stake(&resolver), 5 * MIN_STAKE / 2);
+        assert_eq!(balance(&env, &t.native_token, &resolver), 5 * MIN_STAKE / 2);
+        assert!(contract.try_withdraw_stake(&resolver).is_err());
+
+        // Topping back up to the minimum reactivates
+        mint(&env, &t.native_token, &resolver, MIN_STAKE / 2);
+        contract.increase_stake(&resolver, &(MIN_STAKE / 2));
+        assert!(contract.get_resolver(&resolver).active);
+        let swap_id = new_swap();
+        contract.execute_partial_fill(&swap_id, &resolver, &4_000, &proofs[0], &1u64);
+
+        // Unbonding blocks fills even above the minimum
+        mint(&env, &t.native_token, &resolver, MIN_STAKE);
+        contract.increase_stake(&resolver, &MIN_STAKE);
+        contract.request_unstake(&resolver, &1);
+        assert!(contract.get_resolver(&resolver).active);
+        assert_eq!(
+            contract.try_reserve_fill(&swap_id, &resolver, &4_000, &1u64, &proofs[0]),
+            Err(Ok(ContractError::ResolverNotActive))
+        );
+    }
+
     #[test]
     fn test_invalid_secret_rejection() {
         let env = Env::default();
         env.mock_all_auths();
-        
-        let admin = Address::generate(&env);
+
+        let t = setup(&env);
         let initiator = Address::generate(&env);
-        let token = env.register_stellar_asset_contract(admin.clone());
-        
-        let contract = create_contract(&env);
-        contract.initialize(&admin);
-        
+        let resolver = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, MIN_STAKE);
+
         let amount = 1_000_000_000i128;
         let correct_secret = BytesN::from_array(&env, &[10; 32]);
-        let correct_secret_hash = env.crypto().keccak256(&correct_secret);
+        let correct_secret_hash = env.crypto().keccak256(&correct_secret.clone().into());
         let wrong_secret = BytesN::from_array(&env, &[11; 32]);
         let timelock = env.ledger().timestamp() + 3600;
         let ethereum_address = Bytes::from_slice(&env, &[3u8; 20]);
         let merkle_root = BytesN::from_array(&env, &[12; 32]);
-        
+
         // Mint and initiate swap
-        env.as_contract(&token, || {
-            env.invoke_contract(
-                &token,
-                &"mint",
-                (initiator.clone(), amount).into_val(&env)
-            )
-        });
-        
+        mint(&env, &t.token, &initiator, with_fee(amount));
+
         let swap_id = contract.initiate_swap(
             &initiator,
-            &token,
+            &t.token,
             &amount,
             &correct_secret_hash,
             &timelock,
             &ethereum_address,
-            &false,
-            &merkle_root
+            &merkle_root,
+            &fill_terms(&env, FillMode::FillOrKill, 0),
         );
-        
-        // Try to complete with wrong secret - should panic
-        let result = std::panic::catch_unwind(|| {
-            contract.complete_swap(&swap_id, &wrong_secret);
-        });
+
+        // Try to complete with wrong secret - should fail
+        let result = contract.try_complete_swap(&swap_id, &resolver, &wrong_secret);
         assert!(result.is_err());
-        
+
         // Verify swap state unchanged
-        let swap = contract.get_swap(&swap_id).unwrap();
+        let swap = contract.get_swap(&swap_id);
         assert_eq!(swap.state, SwapState::Initiated);
-        
+
         // Complete with correct secret should work
-        contract.complete_swap(&swap_id, &correct_secret);
-        let completed_swap = contract.get_swap(&swap_id).unwrap();
+        contract.complete_swap(&swap_id, &resolver, &correct_secret);
+        let completed_swap = contract.get_swap(&swap_id);
         assert_eq!(completed_swap.state, SwapState::Completed);
     }
-    
+
     #[test]
     fn test_merkle_proof_validation() {
         let env = Env::default();
         env.mock_all_auths();
-        
-        let admin = Address::generate(&env);
+
+        let t = setup(&env);
         let initiator = Address::generate(&env);
         let resolver = Address::generate(&env);
-        let token = env.register_stellar_asset_contract(admin.clone());
-        
-        let contract = create_contract(&env);
-        contract.initialize(&admin);
-        
-        // Register resolver
-        contract.register_resolver(&resolver, &2_000_000_000i128);
-        
+        let other_resolver = Address::generate(&env);
+        let contract = &t.contract;
+
+        // Register resolvers
+        register_resolver(&env, &t, &resolver, 2_000_000_000i128);
+        register_resolver(&env, &t, &other_resolver, 2_000_000_000i128);
+
         let amount = 6_000_000_000i128;
+        let fill_amount = 2_000_000_000i128;
         let secret_hash = BytesN::from_array(&env, &[13; 32]);
         let timelock = env.ledger().timestamp() + 3600;
         let ethereum_address = Bytes::from_slice(&env, &[4u8; 20]);
-        let valid_merkle_root = BytesN::from_array(&env, &[14; 32]);
-        
+
+        let leaves = [
+            fill_leaf(&env, &resolver, fill_amount, 1),
+            fill_leaf(&env, &other_resolver, fill_amount, 2),
+        ];
+        let (valid_merkle_root, proofs) = build_tree(&env, &leaves);
+
         // Mint and initiate swap
-        env.as_contract(&token, || {
-            env.invoke_contract(
-                &token,
-                &"mint",
-                (initiator.clone(), amount).into_val(&env)
-            )
-        });
-        
+        mint(&env, &t.token, &initiator, with_fee(amount));
+
         let swap_id = contract.initiate_swap(
             &initiator,
-            &token,
+            &t.token,
             &amount,
             &secret_hash,
             &timelock,
             &ethereum_address,
-            &true,
-            &valid_merkle_root
+            &valid_merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 0),
         );
-        
-        // Valid merkle proof
-        let valid_proof = vec![&env, BytesN::from_array(&env, &[15; 32])];
-        let fill_amount = 2_000_000_000i128;
-        
-        // This should work (assuming merkle validation passes)
-        contract.execute_partial_fill(
-            &swap_id,
-            &resolver,
-            &fill_amount,
-            &valid_proof,
-            &1u64
-        );
-        
+
+        // Bogus proof is rejected
+        let bogus_proof = Vec::from_array(&env, [BytesN::from_array(&env, &[15; 32])]);
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver, &fill_amount, &bogus_proof, &1u64)
+            .is_err());
+
+        // A valid proof for another resolver's leaf cannot be reused
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver, &fill_amount, &proofs[1], &2u64)
+            .is_err());
+
+        // Amount is bound into the leaf
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver, &(fill_amount + 1), &proofs[0], &1u64)
+            .is_err());
+
+        // Valid proof succeeds
+        contract.execute_partial_fill(&swap_id, &resolver, &fill_amount, &proofs[0], &1u64);
+
         // Verify partial fill executed
-        let swap = contract.get_swap(&swap_id).unwrap();
+        let swap = contract.get_swap(&swap_id);
         assert_eq!(swap.filled, fill_amount);
         assert_eq!(swap.state, SwapState::PartialFilled);
+        assert_eq!(swap.merkle_version, 1);
+        assert!(contract.is_fill_nonce_used(&swap_id, &resolver, &1u64));
+
+        // Replaying the same leaf is rejected
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver, &fill_amount, &proofs[0], &1u64)
+            .is_err());
+        assert_eq!(contract.get_swap(&swap_id).filled, fill_amount);
     }
-    
+
+    #[test]
+    fn test_partial_fill_history_pagination() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        let contract = &t.contract;
+
+        register_resolver(&env, &t, &resolver, 2_000_000_000i128);
+
+        let amount = 5_000_000_000i128;
+        let fill_amount = 1_000_000_000i128;
+        let leaves: std::vec::Vec<BytesN<32>> = (1..=5u64)
+            .map(|nonce| fill_leaf(&env, &resolver, fill_amount, nonce))
+            .collect();
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[16; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[5u8; 20]),
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 0),
+        );
+        assert_eq!(contract.get_fill_count(&swap_id), 0);
+        assert_eq!(contract.get_partial_fills(&swap_id, &0, &10).len(), 0);
+
+        for (i, proof) in proofs.iter().enumerate() {
+            env.ledger().with_mut(|li| li.timestamp = 100 + i as u64);
+            contract.execute_partial_fill(&swap_id, &resolver, &fill_amount, proof, &(i as u64 + 1));
+        }
+
+        assert_eq!(contract.get_fill_count(&swap_id), 5);
+
+        // Pages are returned oldest first
+        let page = contract.get_partial_fills(&swap_id, &0, &2);
+        assert_eq!(page.len(), 2);
+        assert_eq!(page.get(0).unwrap().timestamp, 100);
+        assert_eq!(page.get(1).unwrap().timestamp, 101);
+
+        let page = contract.get_partial_fills(&swap_id, &4, &10);
+        assert_eq!(page.len(), 1);
+        let last = page.get(0).unwrap();
+        assert_eq!(last.resolver, resolver);
+        assert_eq!(last.amount, fill_amount);
+        assert_eq!(last.timestamp, 104);
+        assert_eq!(last.merkle_proof, proofs[4]);
+
+        // Out of range start yields an empty page
+        assert_eq!(contract.get_partial_fills(&swap_id, &5, &10).len(), 0);
+    }
+
+    #[test]
+    fn test_min_fill_amount_and_fill_step() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, MIN_STAKE);
+
+        let amount = 10_000i128;
+        let leaves = [
+            fill_leaf(&env, &resolver, 1, 1),
+            fill_leaf(&env, &resolver, 2_500, 2),
+            fill_leaf(&env, &resolver, 9_000, 3),
+            fill_leaf(&env, &resolver, 1_000, 4),
+        ];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        let terms = FillTerms {
+            min_fill_amount: 2_000,
+            fill_step: Some(1_000),
+            ..fill_terms(&env, FillMode::AllowPartial, 0)
+        };
+
+        mint(&env, &t.token, &initiator, with_fee(amount));
+
+        // Terms must fit the swap amount
+        let bad_terms = FillTerms {
+            min_fill_amount: amount + 1,
+            ..terms.clone()
+        };
+        assert!(contract
+            .try_initiate_swap(
+                &initiator,
+                &t.token,
+                &amount,
+                &BytesN::from_array(&env, &[21; 32]),
+                &3600u64,
+                &Bytes::from_slice(&env, &[9u8; 20]),
+                &merkle_root,
+                &bad_terms,
+            )
+            .is_err());
+
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[21; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[9u8; 20]),
+            &merkle_root,
+            &terms,
+        );
+        let swap = contract.get_swap(&swap_id);
+        assert_eq!(swap.min_fill_amount, 2_000);
+        assert_eq!(swap.fill_step, Some(1_000));
+
+        // Dust fills and off-lot fills are rejected even with a valid proof
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver, &1, &proofs[0], &1u64)
+            .is_err());
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver, &2_500, &proofs[1], &2u64)
+            .is_err());
+
+        contract.execute_partial_fill(&swap_id, &resolver, &9_000, &proofs[2], &3u64);
+
+        // The final fill may be below the minimum when it clears the remainder
+        contract.execute_partial_fill(&swap_id, &resolver, &1_000, &proofs[3], &4u64);
+        assert_eq!(contract.get_swap(&swap_id).filled, amount);
+    }
+
+    #[test]
+    fn test_multi_secret_fill_segments() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver1 = Address::generate(&env);
+        let resolver2 = Address::generate(&env);
+        let contract = &t.contract;
+
+        register_resolver(&env, &t, &resolver1, 2_000_000_000i128);
+        register_resolver(&env, &t, &resolver2, 2_000_000_000i128);
+
+        // 4 segments -> 5 secrets, the last one reserved for the completing fill
+        let amount = 4_000i128;
+        let parts = 4u32;
+        let secrets: std::vec::Vec<BytesN<32>> = (0..=parts)
+            .map(|i| BytesN::from_array(&env, &[100 + i as u8; 32]))
+            .collect();
+        let leaves: std::vec::Vec<BytesN<32>> = secrets
+            .iter()
+            .enumerate()
+            .map(|(i, secret)| secret_leaf(&env, i as u32, secret))
+            .collect();
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[17; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[6u8; 20]),
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, parts),
+        );
+
+        // Single-secret entrypoints are closed for multi-secret swaps
+        assert!(contract
+            .try_complete_swap(&swap_id, &resolver1, &secrets[4])
+            .is_err());
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver1, &1_000, &proofs[0], &1u64)
+            .is_err());
+
+        // 30% lands in segment 1; the segment 0 secret does not unlock it
+        assert!(contract
+            .try_withdraw_fill(&swap_id, &resolver1, &1_200, &secrets[0], &0, &proofs[0])
+            .is_err());
+        // A secret must match the index it was committed at
+        assert!(contract
+            .try_withdraw_fill(&swap_id, &resolver1, &1_200, &secrets[2], &1, &proofs[1])
+            .is_err());
+        contract.withdraw_fill(&swap_id, &resolver1, &1_200, &secrets[1], &1, &proofs[1]);
+        assert_eq!(balance(&env, &t.token, &resolver1), 1_200);
+        assert!(contract.is_secret_used(&secrets[1]));
+
+        // Another fill within segment 1 would need the already revealed secret
+        assert!(contract
+            .try_withdraw_fill(&swap_id, &resolver2, &100, &secrets[1], &1, &proofs[1])
+            .is_err());
+
+        // 55% lands in segment 2
+        contract.withdraw_fill(&swap_id, &resolver2, &1_000, &secrets[2], &2, &proofs[2]);
+        assert_eq!(balance(&env, &t.token, &resolver2), 1_000);
+
+        // Filling the remainder consumes the final secret
+        assert!(contract
+            .try_withdraw_fill(&swap_id, &resolver2, &1_800, &secrets[3], &3, &proofs[3])
+            .is_err());
+        contract.withdraw_fill(&swap_id, &resolver2, &1_800, &secrets[4], &4, &proofs[4]);
+
+        let swap = contract.get_swap(&swap_id);
+        assert_eq!(swap.state, SwapState::Completed);
+        assert_eq!(swap.filled, amount);
+        assert_eq!(swap.last_secret_index, 4);
+        assert_eq!(balance(&env, &t.token, &resolver2), 2_800);
+        assert_eq!(contract.get_fill_count(&swap_id), 3);
+
+        // Segment rewards come out of the maker fee; the last segment takes the rest
+        assert_eq!(contract.get_pending_rewards(&resolver1, &t.token), 1);
+        assert_eq!(contract.get_pending_rewards(&resolver2, &t.token), 3);
+        assert_eq!(balance(&env, &t.token, &contract.address), 4);
+        assert_eq!(swap.fee, 0);
+    }
+
+    #[test]
+    fn test_dutch_auction_fills() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver1 = Address::generate(&env);
+        let resolver2 = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver1, MIN_STAKE);
+        register_resolver(&env, &t, &resolver2, MIN_STAKE);
+
+        let amount = 10_000i128;
+        let leaves = [
+            fill_leaf(&env, &resolver1, 4_000, 1),
+            fill_leaf(&env, &resolver2, 6_000, 2),
+        ];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[22; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[11u8; 20]),
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 0),
+        );
+
+        assert!(contract.try_get_current_rate(&swap_id).is_err());
+        assert!(contract
+            .try_start_auction(&swap_id, &10_000, &12_000, &300, &Vec::new(&env), &None)
+            .is_err());
+        contract.start_auction(&swap_id, &12_000, &10_000, &300, &Vec::new(&env), &None);
+        assert!(contract
+            .try_start_auction(&swap_id, &12_000, &10_000, &300, &Vec::new(&env), &None)
+            .is_err());
+
+        // Price curve decays linearly and is clamped at the end rate
+        assert_eq!(contract.get_current_rate(&swap_id), 12_000);
+        env.ledger().with_mut(|li| li.timestamp = 1_075);
+        assert_eq!(contract.get_current_rate(&swap_id), 11_500);
+
+        // Fixed-reward fills cannot bypass the auction
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver1, &4_000, &proofs[0], &1u64)
+            .is_err());
+
+        // Bids below the current rate are rejected
+        assert!(contract
+            .try_place_bid(&swap_id, &resolver1, &11_000, &4_000, &proofs[0], &1u64)
+            .is_err());
+
+        // Accepted at the current rate, not the bid
+        env.ledger().with_mut(|li| li.timestamp = 1_150);
+        let rate = contract.place_bid(&swap_id, &resolver1, &11_200, &4_000, &proofs[0], &1u64);
+        assert_eq!(rate, 11_000);
+
+        env.ledger().with_mut(|li| li.timestamp = 2_000);
+        assert_eq!(contract.get_current_rate(&swap_id), 10_000);
+        contract.place_bid(&swap_id, &resolver2, &10_000, &6_000, &proofs[1], &2u64);
+
+        let fills = contract.get_partial_fills(&swap_id, &0, &10);
+        assert_eq!(fills.get(0).unwrap().rate, 11_000);
+        assert_eq!(fills.get(1).unwrap().rate, 10_000);
+        assert_eq!(contract.get_swap(&swap_id).filled, amount);
+    }
+
+    #[test]
+    fn test_auction_curve_and_fee_oracle() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let oracle = Address::generate(&env);
+        let contract = &t.contract;
+
+        let amount = 10_000i128;
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[23; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[11u8; 20]),
+            &BytesN::from_array(&env, &[0; 32]),
+            &fill_terms(&env, FillMode::AllowPartial, 0),
+        );
+
+        // Points outside the rate band are rejected
+        let bad_points = Vec::from_array(&env, [AuctionPoint { time_offset: 100, rate: 13_000 }]);
+        assert!(contract
+            .try_start_auction(&swap_id, &12_000, &10_000, &300, &bad_points, &None)
+            .is_err());
+
+        let points = Vec::from_array(
+            &env,
+            [
+                AuctionPoint { time_offset: 100, rate: 11_800 },
+                AuctionPoint { time_offset: 200, rate: 10_200 },
+            ],
+        );
+        // A bump can't exceed the rate band
+        let oversized = GasBump {
+            gas_bump_estimate: 2_001,
+            gas_price_estimate: 1_000,
+        };
+        assert_eq!(
+            contract.try_start_auction(&swap_id, &12_000, &10_000, &300, &points, &Some(oversized)),
+            Err(Ok(ContractError::InvalidAuctionParams))
+        );
+
+        let gas_bump = GasBump {
+            gas_bump_estimate: 100,
+            gas_price_estimate: 1_000,
+        };
+        contract.start_auction(&swap_id, &12_000, &10_000, &300, &points, &Some(gas_bump));
+        assert_eq!(contract.get_auction(&swap_id).points, points);
+
+        env.ledger().with_mut(|li| li.timestamp = 1_150);
+        let quote = contract.quote_fill(&swap_id, &5_000);
+        assert_eq!(quote.rate, 11_000);
+        assert_eq!(quote.taking_amount, 5_500);
+        assert!(contract.try_quote_fill(&swap_id, &(amount + 1)).is_err());
+
+        // Only the configured oracle may report fees
+        assert!(contract.try_report_network_fee(&2_000).is_err());
+        contract.set_fee_oracle(&Some(oracle));
+        contract.report_network_fee(&2_000);
+        assert_eq!(contract.get_network_fee().unwrap().fee, 2_000);
+
+        // Twice the estimated gas price doubles the bump
+        assert_eq!(contract.quote_fill(&swap_id, &5_000).rate, 10_800);
+
+        // Stale reports are ignored, and the bump never crosses the end rate
+        env.ledger().with_mut(|li| li.timestamp = 1_290);
+        contract.report_network_fee(&100_000);
+        assert_eq!(contract.get_current_rate(&swap_id), 10_000);
+        env.ledger().with_mut(|li| li.timestamp = 2_000);
+        assert!(contract.get_network_fee().is_none());
+        assert_eq!(contract.get_current_rate(&swap_id), 10_000);
+
+        // Extreme reports saturate rather than overflow
+        contract.report_network_fee(&i128::MAX);
+        assert_eq!(contract.get_current_rate(&swap_id), 10_000);
+        assert_eq!(contract.quote_fill(&swap_id, &5_000).rate, 10_000);
+    }
+
     #[test]
     fn test_analytics_tracking() {
         let env = Env::default();
         env.mock_all_auths();
-        
-        let admin = Address::generate(&env);
+
+        let t = setup(&env);
         let initiator = Address::generate(&env);
-        let token = env.register_stellar_asset_contract(admin.clone());
-        
-        let contract = create_contract(&env);
-        contract.initialize(&admin);
-        
+        let resolver = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, MIN_STAKE);
+
         // Check initial analytics
         let initial_analytics = contract.get_analytics();
         assert_eq!(initial_analytics.total_swaps, 0);
         assert_eq!(initial_analytics.total_volume, 0);
-        
+
         // Execute multiple swaps
         for i in 0..3 {
             let amount = (i + 1) as i128 * 1_000_000_000i128;
             let secret = BytesN::from_array(&env, &[(i + 20) as u8; 32]);
-            let secret_hash = env.crypto().keccak256(&secret);
+            let secret_hash = env.crypto().keccak256(&secret.clone().into());
             let timelock = env.ledger().timestamp() + 3600;
             let ethereum_address = Bytes::from_slice(&env, &[(i + 5) as u8; 20]);
             let merkle_root = BytesN::from_array(&env, &[(i + 25) as u8; 32]);
-            
+
             // Mint tokens
-            env.as_contract(&token, || {
-                env.invoke_contract(
-                    &token,
-                    &"mint",
-                    (initiator.clone(), amount).into_val(&env)
-                )
-            });
-            
+            mint(&env, &t.token, &initiator, with_fee(amount));
+
             // Initiate and complete swap
             let swap_id = contract.initiate_swap(
                 &initiator,
-                &token,
+                &t.token,
                 &amount,
                 &secret_hash,
                 &timelock,
                 &ethereum_address,
-                &false,
-                &merkle_root
+                &merkle_root,
+                &fill_terms(&env, FillMode::FillOrKill, 0),
             );
-            
-            contract.complete_swap(&swap_id, &secret);
+
+            // Completion times of 100, 200 and 300 seconds
+            env.ledger().with_mut(|li| li.timestamp += (i + 1) * 100);
+            contract.complete_swap(&swap_id, &resolver, &secret);
         }
-        
+
         // Check updated analytics
         let final_analytics = contract.get_analytics();
         assert_eq!(final_analytics.total_swaps, 3);
         assert_eq!(final_analytics.total_volume, 6_000_000_000i128); // 1+2+3 billion
         assert_eq!(final_analytics.success_rate, 10000); // 100%
+        assert_eq!(final_analytics.completed_swaps, 3);
+        assert_eq!(final_analytics.average_completion_time, 200);
+
+        // Refunds and expiries are counted apart and both lower the success rate
+        let mut lapsed = std::vec::Vec::new();
+        for i in 0..2u8 {
+            mint(&env, &t.token, &initiator, with_fee(1_000));
+            lapsed.push(contract.initiate_swap(
+                &initiator,
+                &t.token,
+                &1_000,
+                &BytesN::from_array(&env, &[40 + i; 32]),
+                &3600u64,
+                &Bytes::from_slice(&env, &[19u8; 20]),
+                &BytesN::from_array(&env, &[0; 32]),
+                &fill_terms(&env, FillMode::FillOrKill, 0),
+            ));
+        }
+        assert_eq!(
+            contract.try_expire_swap(&lapsed[0]),
+            Err(Ok(ContractError::TimelockNotExpired))
+        );
+
+        env.ledger().with_mut(|li| li.timestamp += 3600);
+        contract.expire_swap(&lapsed[0]);
+        contract.refund_swap(&lapsed[1]);
+        assert_eq!(contract.get_swap(&lapsed[0]).state, SwapState::Expired);
+        assert!(contract.try_expire_swap(&lapsed[1]).is_err());
+        assert_eq!(balance(&env, &t.token, &initiator), 2 * with_fee(1_000));
+
+        let final_analytics = contract.get_analytics();
+        assert_eq!(final_analytics.expired_swaps, 1);
+        assert_eq!(final_analytics.refunded_swaps, 1);
+        assert_eq!(final_analytics.success_rate, 6000);
+        assert_eq!(final_analytics.average_completion_time, 200);
     }
-    
+
     #[test]
     fn test_contract_pause_functionality() {
         let env = Env::default();
         env.mock_all_auths();
-        
-        let admin = Address::generate(&env);
+
+        let t = setup(&env);
         let user = Address::generate(&env);
-        let token = env.register_stellar_asset_contract(admin.clone());
-        
-        let contract = create_contract(&env);
-        contract.initialize(&admin);
-        
+        let contract = &t.contract;
+
         // Contract should not be paused initially
         assert!(!contract.is_paused());
-        
+
         // Admin can pause
         contract.pause();
         assert!(contract.is_paused());
-        
+
         // Operations should fail when paused
         let amount = 1_000_000_000i128;
         let secret_hash = BytesN::from_array(&env, &[30; 32]);
         let timelock = env.ledger().timestamp() + 3600;
         let ethereum_address = Bytes::from_slice(&env, &[10u8; 20]);
         let merkle_root = BytesN::from_array(&env, &[31; 32]);
-        
-        let result = std::panic::catch_unwind(|| {
+
+        mint(&env, &t.token, &user, with_fee(amount));
+
+        let result = contract.try_initiate_swap(
+            &user,
+            &t.token,
+            &amount,
+            &secret_hash,
+            &timelock,
+            &ethereum_address,
+            &merkle_root,
+            &fill_terms(&env, FillMode::FillOrKill, 0),
+        );
+        assert!(result.is_err());
+
+        // Admin can unpause
+        contract.unpause();
+        assert!(!contract.is_paused());
+    }
+
+    #[test]
+    fn test_resolver_slashing() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        let arbiter = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, 2 * MIN_STAKE);
+
+        let amount = 10_000i128;
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[33; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[12u8; 20]),
+            &BytesN::from_array(&env, &[0; 32]),
+            &fill_terms(&env, FillMode::FillOrKill, 0),
+        );
+        let reason = Symbol::new(&env, "no_delivery");
+
+        // Only the admin and arbiters can slash
+        assert!(contract
+            .try_slash_resolver(&arbiter, &resolver, &swap_id, &MIN_STAKE, &reason)
+            .is_err());
+        contract.set_arbiter(&arbiter, &true);
+        contract.slash_resolver(&arbiter, &resolver, &swap_id, &MIN_STAKE, &reason);
+
+        let info = contract.get_resolver(&resolver);
+        assert_eq!(info.stake, MIN_STAKE);
+        assert!(info.active);
+        assert_eq!(balance(&env, &t.native_token, &initiator), MIN_STAKE / 2);
+        assert_eq!(contract.get_insurance_fund(), MIN_STAKE / 2);
+
+        // A single slash is capped at half the bonded stake
+        assert_eq!(
+            contract.try_slash_resolver(&t.admin, &resolver, &swap_id, &(MIN_STAKE / 2 + 1), &reason),
+            Err(Ok(ContractError::LimitExceeded))
+        );
+
+        // Unbonding stake stays slashable, and dropping below the minimum deactivates
+        contract.request_unstake(&resolver, &(MIN_STAKE / 2));
+        contract.set_insurance_rate(&10000);
+        contract.slash_resolver(&t.admin, &resolver, &swap_id, &(MIN_STAKE / 2), &reason);
+        let info = contract.get_resolver(&resolver);
+        assert_eq!(info.stake, 0);
+        assert_eq!(info.unbonding_amount, MIN_STAKE / 2);
+        assert!(!info.active);
+        assert_eq!(balance(&env, &t.native_token, &initiator), MIN_STAKE / 2);
+        assert_eq!(contract.get_insurance_fund(), MIN_STAKE);
+
+        let history = contract.get_slash_history(&resolver);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(0).unwrap().slashed_by, arbiter);
+        assert_eq!(history.get(1).unwrap().slashed_by, t.admin);
+        assert_eq!(history.get(1).unwrap().to_insurance, MIN_STAKE / 2);
+
+        // Revoked arbiters lose access
+        contract.set_arbiter(&arbiter, &false);
+        assert!(contract
+            .try_slash_resolver(&arbiter, &resolver, &swap_id, &1, &reason)
+            .is_err());
+
+        contract.withdraw_insurance(&initiator, &MIN_STAKE);
+        assert_eq!(contract.get_insurance_fund(), 0);
+        assert_eq!(balance(&env, &t.native_token, &initiator), 3 * MIN_STAKE / 2);
+        assert!(contract.try_withdraw_insurance(&initiator, &1).is_err());
+    }
+
+    #[test]
+    fn test_resolver_reputation() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver1 = Address::generate(&env);
+        let resolver2 = Address::generate(&env);
+        let completer = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver1, MIN_STAKE);
+        register_resolver(&env, &t, &resolver2, MIN_STAKE);
+        register_resolver(&env, &t, &completer, MIN_STAKE);
+
+        // Completed fills raise reputation
+        let secret = BytesN::from_array(&env, &[40; 32]);
+        let swap_id = setup_filled_swap(&env, &t, &initiator, &resolver1, &resolver2, &secret);
+        contract.complete_swap(&swap_id, &completer, &secret);
+        let info = contract.get_resolver(&resolver1);
+        assert_eq!(info.reputation, 1010);
+        assert_eq!(info.success_rate, 10_000);
+
+        // So does completing the swap and taking its remainder
+        let info = contract.get_resolver(&completer);
+        assert_eq!(info.reputation, 1010);
+        assert_eq!(info.total_volume, 5_000);
+
+        // Refunds after a fill count against every filler
+        let secret = BytesN::from_array(&env, &[41; 32]);
+        let swap_id = setup_filled_swap(&env, &t, &initiator, &resolver1, &resolver2, &secret);
+        env.ledger().with_mut(|li| li.timestamp = 1_000 + 3601);
+        contract.refund_swap(&swap_id);
+        for resolver in [&resolver1, &resolver2] {
+            let info = contract.get_resolver(resolver);
+            assert_eq!(info.reputation, 909);
+            assert_eq!(info.success_rate, 9_000);
+        }
+
+        let leaves = [fill_leaf(&env, &resolver1, 3_000, 1)];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        let new_swap = || {
+            mint(&env, &t.token, &initiator, with_fee(10_000));
             contract.initiate_swap(
-                &user,
-                &token,
+                &initiator,
+                &t.token,
+                &10_000,
+                &BytesN::from_array(&env, &[42; 32]),
+                &3600u64,
+                &Bytes::from_slice(&env, &[13u8; 20]),
+                &merkle_root,
+                &fill_terms(&env, FillMode::AllowPartial, 0),
+            )
+        };
+
+        // Resolvers under the threshold can't fill
+        contract.set_min_reputation(&950);
+        let swap_id = new_swap();
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &resolver1, &3_000, &proofs[0], &1u64)
+            .is_err());
+
+        // Decay pulls reputation halfway back to the base after 30 days
+        env.ledger().with_mut(|li| li.timestamp = 1_000 + 3601 + 30 * 24 * 60 * 60);
+        assert_eq!(contract.get_resolver(&resolver1).reputation, 955);
+        let swap_id = new_swap();
+        contract.execute_partial_fill(&swap_id, &resolver1, &3_000, &proofs[0], &1u64);
+    }
+
+    #[test]
+    fn test_top_resolvers() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver1 = Address::generate(&env);
+        let resolver2 = Address::generate(&env);
+        let resolver3 = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver1, 3 * MIN_STAKE);
+        register_resolver(&env, &t, &resolver2, MIN_STAKE);
+        register_resolver(&env, &t, &resolver3, 2 * MIN_STAKE);
+        assert_eq!(contract.get_analytics().total_resolvers, 3);
+        assert_eq!(contract.get_active_resolver_count(), 3);
+
+        let secret = BytesN::from_array(&env, &[50; 32]);
+        let swap_id = setup_filled_swap(&env, &t, &initiator, &resolver1, &resolver2, &secret);
+        contract.complete_swap(&swap_id, &resolver3, &secret);
+
+        assert_eq!(
+            contract.get_top_resolvers(&0, &MAX_RESOLVERS_PAGE, &2, &ResolverSort::Stake),
+            Vec::from_array(&env, [resolver1.clone(), resolver3.clone()])
+        );
+        // resolver3 completed the swap and took its 5_000 remainder
+        assert_eq!(
+            contract.get_top_resolvers(&0, &MAX_RESOLVERS_PAGE, &3, &ResolverSort::Volume),
+            Vec::from_array(&env, [resolver3.clone(), resolver1.clone(), resolver2.clone()])
+        );
+        // Ties keep registration order
+        assert_eq!(
+            contract.get_top_resolvers(&0, &MAX_RESOLVERS_PAGE, &10, &ResolverSort::Reputation),
+            Vec::from_array(&env, [resolver1.clone(), resolver2.clone(), resolver3.clone()])
+        );
+        assert_eq!(contract.get_top_resolvers(&0, &MAX_RESOLVERS_PAGE, &0, &ResolverSort::Stake).len(), 0);
+
+        // Inactive resolvers drop out of the ranking and the active count
+        contract.request_unstake(&resolver3, &(3 * MIN_STAKE / 2));
+        assert_eq!(contract.get_active_resolver_count(), 2);
+        assert_eq!(contract.get_analytics().total_resolvers, 3);
+        assert_eq!(
+            contract.get_top_resolvers(&0, &MAX_RESOLVERS_PAGE, &3, &ResolverSort::Stake),
+            Vec::from_array(&env, [resolver1.clone(), resolver2.clone()])
+        );
+
+        mint(&env, &t.native_token, &resolver3, MIN_STAKE);
+        contract.increase_stake(&resolver3, &MIN_STAKE);
+        assert_eq!(contract.get_active_resolver_count(), 3);
+
+        // Pages rank their own slice of the registration order
+        assert_eq!(
+            contract.get_top_resolvers(&1, &2, &1, &ResolverSort::Stake),
+            Vec::from_array(&env, [resolver3.clone()])
+        );
+        assert_eq!(contract.get_top_resolvers(&3, &MAX_RESOLVERS_PAGE, &3, &ResolverSort::Stake).len(), 0);
+
+        // Larger limits read a single page; later registrations need the next one
+        for _ in 3..MAX_RESOLVERS_PAGE {
+            register_resolver(&env, &t, &Address::generate(&env), MIN_STAKE);
+        }
+        let late = Address::generate(&env);
+        register_resolver(&env, &t, &late, 4 * MIN_STAKE);
+        let page = contract.get_top_resolvers(&0, &50, &50, &ResolverSort::Stake);
+        assert_eq!(page.len(), MAX_RESOLVERS_PAGE);
+        assert!(!page.contains(&late));
+        assert_eq!(
+            contract.get_top_resolvers(&MAX_RESOLVERS_PAGE, &MAX_RESOLVERS_PAGE, &1, &ResolverSort::Stake),
+            Vec::from_array(&env, [late.clone()])
+        );
+    }
+
+    #[test]
+    fn test_resolver_whitelist() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let existing = Address::generate(&env);
+        let approved = Address::generate(&env);
+        let unapproved = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &existing, MIN_STAKE);
+
+        contract.set_whitelist_mode(&true);
+        mint(&env, &t.native_token, &unapproved, MIN_STAKE);
+        assert_eq!(
+            contract.try_register_resolver(&unapproved, &MIN_STAKE),
+            Err(Ok(ContractError::Unauthorized))
+        );
+        contract.approve_resolver(&approved);
+        register_resolver(&env, &t, &approved, MIN_STAKE);
+
+        // Resolvers registered before the switch keep filling
+        let leaves = [
+            fill_leaf(&env, &existing, 3_000, 1),
+            fill_leaf(&env, &approved, 2_000, 2),
+        ];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        mint(&env, &t.token, &initiator, with_fee(10_000));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &10_000,
+            &BytesN::from_array(&env, &[60; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[14u8; 20]),
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 0),
+        );
+
+        // Suspension blocks fills until lifted
+        contract.suspend_resolver(&existing, &true);
+        assert_eq!(
+            contract.try_execute_partial_fill(&swap_id, &existing, &3_000, &proofs[0], &1u64),
+            Err(Ok(ContractError::ResolverNotActive))
+        );
+        contract.suspend_resolver(&existing, &false);
+        contract.execute_partial_fill(&swap_id, &existing, &3_000, &proofs[0], &1u64);
+
+        // Revocation blocks fills and re-registration
+        contract.revoke_resolver(&approved);
+        assert_eq!(
+            contract.try_execute_partial_fill(&swap_id, &approved, &2_000, &proofs[1], &2u64),
+            Err(Ok(ContractError::ResolverNotActive))
+        );
+        contract.set_whitelist_mode(&false);
+        let revoked = Address::generate(&env);
+        contract.revoke_resolver(&revoked);
+        mint(&env, &t.native_token, &revoked, MIN_STAKE);
+        assert_eq!(
+            contract.try_register_resolver(&revoked, &MIN_STAKE),
+            Err(Ok(ContractError::Unauthorized))
+        );
+        contract.register_resolver(&unapproved, &MIN_STAKE);
+    }
+
+    #[test]
+    fn test_resolver_profile() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let t = setup(&env);
+        let resolver = Address::generate(&env);
+        let stranger = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, MIN_STAKE);
+        assert_eq!(contract.get_resolver_profile(&resolver), None);
+
+        let profile = ResolverProfile {
+            display_name: String::from_str(&env, "Acme Resolver"),
+            api_uri: String::from_str(&env, "https://resolver.example.com/v1"),
+            supported_tokens: Vec::from_array(&env, [t.token.clone()]),
+            chain_ids: Vec::from_array(&env, [1u64, 11155111]),
+            max_fill_size: Some(50_000),
+            updated_at: 0,
+        };
+        contract.set_resolver_profile(&resolver, &profile);
+        let stored = contract.get_resolver_profile(&resolver).unwrap();
+        assert_eq!(stored.display_name, profile.display_name);
+        assert_eq!(stored.chain_ids, profile.chain_ids);
+        assert_eq!(stored.updated_at, 1_000);
+
+        // Only registered resolvers have profiles
+        assert!(contract.try_set_resolver_profile(&stranger, &profile).is_err());
+
+        let mut oversized = profile.clone();
+        oversized.display_name = String::from_str(&env, &"x".repeat(65));
+        assert_eq!(
+            contract.try_set_resolver_profile(&resolver, &oversized),
+            Err(Ok(ContractError::LimitExceeded))
+        );
+
+        let mut oversized = profile.clone();
+        let mut chain_ids = Vec::new(&env);
+        for chain_id in 0..33u64 {
+            chain_ids.push_back(chain_id);
+        }
+        oversized.chain_ids = chain_ids;
+        assert_eq!(
+            contract.try_set_resolver_profile(&resolver, &oversized),
+            Err(Ok(ContractError::LimitExceeded))
+        );
+
+        let mut invalid = profile.clone();
+        invalid.max_fill_size = Some(0);
+        assert_eq!(
+            contract.try_set_resolver_profile(&resolver, &invalid),
+            Err(Ok(ContractError::InvalidAmount))
+        );
+
+        let mut unlimited = profile;
+        unlimited.max_fill_size = None;
+        contract.set_resolver_profile(&resolver, &unlimited);
+        assert_eq!(contract.get_resolver_profile(&resolver).unwrap().max_fill_size, None);
+    }
+
+    #[test]
+    fn test_exclusive_resolvers() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let rfq = Address::generate(&env);
+        let other = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &rfq, MIN_STAKE);
+        register_resolver(&env, &t, &other, MIN_STAKE);
+
+        let amount = 10_000i128;
+        let leaves = [
+            fill_leaf(&env, &rfq, 3_000, 1),
+            fill_leaf(&env, &other, 2_000, 2),
+            fill_leaf(&env, &other, 1_000, 3),
+        ];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        let secret = BytesN::from_array(&env, &[70; 32]);
+        let terms = FillTerms {
+            exclusive_resolvers: Vec::from_array(&env, [rfq.clone()]),
+            exclusivity_deadline: 1_600,
+            ..fill_terms(&env, FillMode::AllowPartial, 0)
+        };
+
+        // The deadline must fall inside the swap's timelock
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let late = FillTerms {
+            exclusivity_deadline: 1_000 + 3600 + 1,
+            ..terms.clone()
+        };
+        assert!(contract
+            .try_initiate_swap(
+                &initiator,
+                &t.token,
                 &amount,
-                &secret_hash,
-                &timelock,
-                &ethereum_address,
-                &false,
-                &merkle_root
-            );
+                &env.crypto().keccak256(&secret.clone().into()),
+                &3600u64,
+                &Bytes::from_slice(&env, &[15u8; 20]),
+                &merkle_root,
+                &late,
+            )
+            .is_err());
+
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &env.crypto().keccak256(&secret.clone().into()),
+            &3600u64,
+            &Bytes::from_slice(&env, &[15u8; 20]),
+            &merkle_root,
+            &terms,
+        );
+
+        // Only the RFQ counterparty can fill, reserve or complete before the deadline
+        assert_eq!(
+            contract.try_execute_partial_fill(&swap_id, &other, &2_000, &proofs[1], &2u64),
+            Err(Ok(ContractError::Unauthorized))
+        );
+        assert!(contract
+            .try_reserve_fill(&swap_id, &other, &2_000, &2u64, &proofs[1])
+            .is_err());
+        assert!(contract.try_complete_swap(&swap_id, &other, &secret).is_err());
+        let plan = contract.plan_fills(
+            &swap_id,
+            &Vec::from_array(&env, [rfq.clone(), other.clone()]),
+            &FillWeighting::Stake,
+        );
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan.get(0).unwrap().resolver, rfq);
+        contract.execute_partial_fill(&swap_id, &rfq, &3_000, &proofs[0], &1u64);
+
+        // Afterwards the order is open to every active resolver
+        env.ledger().with_mut(|li| li.timestamp = 1_600);
+        contract.execute_partial_fill(&swap_id, &other, &2_000, &proofs[1], &2u64);
+
+        // Unregistered and suspended addresses still can't complete
+        let stranger = Address::generate(&env);
+        assert_eq!(
+            contract.try_complete_swap(&swap_id, &stranger, &secret),
+            Err(Ok(ContractError::ResolverNotFound))
+        );
+        contract.suspend_resolver(&other, &true);
+        assert!(contract.try_complete_swap(&swap_id, &other, &secret).is_err());
+        contract.suspend_resolver(&other, &false);
+
+        contract.complete_swap(&swap_id, &other, &secret);
+        assert_eq!(contract.get_swap(&swap_id).state, SwapState::Completed);
+    }
+
+    #[test]
+    fn test_delegated_staking() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        let delegator1 = Address::generate(&env);
+        let delegator2 = Address::generate(&env);
+        let contract = &t.contract;
+        contract.set_delegator_unbonding_period(&100);
+
+        // Own stake must cover a quarter of the minimum
+        assert_eq!(
+            contract.try_register_resolver(&resolver, &(MIN_STAKE / 4 - 1)),
+            Err(Ok(ContractError::InsufficientStake))
+        );
+
+        // Own stake under the minimum registers inactive
+        register_resolver(&env, &t, &resolver, MIN_STAKE / 2);
+        assert!(!contract.get_resolver(&resolver).active);
+
+        // Delegations count toward the minimum
+        mint(&env, &t.native_token, &delegator1, MIN_STAKE / 2);
+        contract.delegate(&delegator1, &resolver, &(MIN_STAKE / 2));
+        assert!(contract.get_resolver(&resolver).active);
+        mint(&env, &t.native_token, &delegator2, MIN_STAKE);
+        contract.delegate(&delegator2, &resolver, &MIN_STAKE);
+        assert_eq!(contract.get_resolver(&resolver).delegated_stake, 3 * MIN_STAKE / 2);
+        assert_eq!(contract.get_delegation(&delegator2, &resolver).unwrap().amount, MIN_STAKE);
+
+        // Commission increases wait out the delegator unbonding period
+        contract.set_commission_rate(&resolver, &1000);
+        let pool = contract.get_delegation_pool(&resolver);
+        assert_eq!(pool.commission_rate, 0);
+        assert_eq!(pool.pending_commission_rate, 1000);
+        assert_eq!(pool.commission_effective_at, 1_100);
+        env.ledger().with_mut(|li| li.timestamp = 1_100);
+        assert_eq!(contract.get_delegation_pool(&resolver).commission_rate, 1000);
+
+        // Rewards split by effective stake after a 10% commission
+        let amount = 2_000_000i128;
+        let leaves = [fill_leaf(&env, &resolver, 1_000_000, 1)];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        let secret = BytesN::from_array(&env, &[80; 32]);
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &env.crypto().keccak256(&secret.clone().into()),
+            &3600u64,
+            &Bytes::from_slice(&env, &[16u8; 20]),
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 0),
+        );
+        contract.execute_partial_fill(&swap_id, &resolver, &1_000_000, &proofs[0], &1u64);
+        contract.complete_swap(&swap_id, &resolver, &secret);
+
+        // 2_000 of rewards for the fill and the remainder: 1_500 delegated
+        // share, 150 commission. The resolver's slice is credited alongside its share
+        assert_eq!(contract.get_pending_rewards(&resolver, &t.token), 1_000_650);
+        assert_eq!(contract.get_delegation_rewards(&delegator1, &resolver, &t.token), 450);
+        assert_eq!(contract.get_delegation_rewards(&delegator2, &resolver, &t.token), 900);
+        assert_eq!(
+            contract.claim_delegation_rewards(&delegator1, &resolver, &t.token),
+            450
+        );
+        assert_eq!(balance(&env, &t.token, &delegator1), 450);
+        assert_eq!(contract.get_delegation_rewards(&delegator1, &resolver, &t.token), 0);
+
+        // Slashing hits delegators in proportion to their stake
+        let reason = Symbol::new(&env, "no_delivery");
+        contract.slash_resolver(&t.admin, &resolver, &swap_id, &(MIN_STAKE / 2), &reason);
+        let info = contract.get_resolver(&resolver);
+        assert_eq!(info.stake, 3 * MIN_STAKE / 8);
+        assert_eq!(info.delegated_stake, 9 * MIN_STAKE / 8);
+        let delegation1 = contract.get_delegation(&delegator1, &resolver).unwrap();
+        let delegation2 = contract.get_delegation(&delegator2, &resolver).unwrap();
+        assert_eq!(delegation1.amount, 3 * MIN_STAKE / 8);
+        assert_eq!(delegation2.amount, 3 * MIN_STAKE / 4);
+
+        // Delegators unbond on their own period; leaving drops the resolver below the minimum
+        contract.undelegate(&delegator2, &resolver, &(3 * MIN_STAKE / 4));
+        assert!(!contract.get_resolver(&resolver).active);
+        let delegation = contract.get_delegation(&delegator2, &resolver).unwrap();
+        assert_eq!(delegation.amount, 0);
+        assert_eq!(delegation.unbonding_amount, 3 * MIN_STAKE / 4);
+        assert_eq!(delegation.unbonding_until, 1_200);
+        assert!(contract.try_withdraw_delegation(&delegator2, &resolver).is_err());
+
+        env.ledger().with_mut(|li| li.timestamp = 1_200);
+        assert_eq!(contract.withdraw_delegation(&delegator2, &resolver), 3 * MIN_STAKE / 4);
+        assert_eq!(balance(&env, &t.native_token, &delegator2), 3 * MIN_STAKE / 4);
+        assert_eq!(contract.get_delegation(&delegator2, &resolver), None);
+
+        // Rewards settled on undelegation stay claimable
+        assert_eq!(contract.claim_rewards(&delegator2, &t.token), 900);
+
+        // Cuts apply immediately and cancel a scheduled increase
+        contract.set_commission_rate(&resolver, &5000);
+        contract.set_commission_rate(&resolver, &500);
+        let pool = contract.get_delegation_pool(&resolver);
+        assert_eq!(pool.commission_rate, 500);
+        assert_eq!(pool.commission_effective_at, 0);
+    }
+
+    #[test]
+    fn test_resolver_operators() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        let operator = Address::generate(&env);
+        let rotated = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, MIN_STAKE);
+
+        // Operators must be fresh addresses and only one resolver's
+        let other = Address::generate(&env);
+        register_resolver(&env, &t, &other, MIN_STAKE);
+        assert!(contract.try_add_operator(&resolver, &other).is_err());
+        assert!(contract.try_add_operator(&resolver, &resolver).is_err());
+        contract.add_operator(&resolver, &operator);
+        assert!(contract.try_add_operator(&other, &operator).is_err());
+        assert!(contract.try_register_resolver(&operator, &MIN_STAKE).is_err());
+        assert_eq!(contract.get_operators(&resolver), Vec::from_array(&env, [operator.clone()]));
+
+        // Fills and completion by the operator are accounted to the resolver
+        let amount = 2_000_000i128;
+        let leaves = [
+            fill_leaf(&env, &resolver, 1_000_000, 1),
+            fill_leaf(&env, &resolver, 1_000_000, 2),
+        ];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        let secret = BytesN::from_array(&env, &[90; 32]);
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &env.crypto().keccak256(&secret.clone().into()),
+            &3600u64,
+            &Bytes::from_slice(&env, &[17u8; 20]),
+            &merkle_root,
+            &fill_terms(&env, FillMode::AllowPartial, 0),
+        );
+        contract.execute_partial_fill(&swap_id, &operator, &1_000_000, &proofs[0], &1u64);
+        assert_eq!(contract.get_pending_payouts(&swap_id).get(resolver.clone()), Some(1_000_000));
+        assert_eq!(contract.get_pending_payouts(&swap_id).get(operator.clone()), None);
+
+        // Rotation swaps the key; the old one stops working
+        contract.rotate_operator(&resolver, &operator, &rotated);
+        assert_eq!(contract.get_operators(&resolver), Vec::from_array(&env, [rotated.clone()]));
+        assert!(contract
+            .try_execute_partial_fill(&swap_id, &operator, &1_000_000, &proofs[1], &2u64)
+            .is_err());
+        contract.execute_partial_fill(&swap_id, &rotated, &1_000_000, &proofs[1], &2u64);
+        contract.complete_swap(&swap_id, &rotated, &secret);
+        assert_eq!(contract.get_swap(&swap_id).state, SwapState::Completed);
+        assert_eq!(contract.get_resolver(&resolver).total_volume, amount);
+
+        // Slice and reward claims stay with the owner key
+        assert!(contract.try_claim_rewards(&rotated, &t.token).is_err());
+        assert_eq!(contract.claim_rewards(&resolver, &t.token), with_fee(amount));
+        assert_eq!(balance(&env, &t.token, &resolver), with_fee(amount));
+        assert_eq!(balance(&env, &t.token, &rotated), 0);
+
+        // Revoked operators can no longer act, and only the owner's operators can be revoked
+        assert!(contract.try_revoke_operator(&other, &rotated).is_err());
+        contract.revoke_operator(&resolver, &rotated);
+        assert_eq!(contract.get_operators(&resolver).len(), 0);
+        assert!(contract.try_revoke_operator(&resolver, &rotated).is_err());
+
+        // Each resolver has a bounded number of operators
+        for _ in 0..5 {
+            contract.add_operator(&resolver, &Address::generate(&env));
+        }
+        assert_eq!(
+            contract.try_add_operator(&resolver, &Address::generate(&env)),
+            Err(Ok(ContractError::LimitExceeded))
+        );
+    }
+
+    #[test]
+    fn test_resolver_jailing() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, 2 * MIN_STAKE);
+        contract.set_jail_policy(&2, &100, &500);
+
+        let amount = 10_000i128;
+        mint(&env, &t.token, &initiator, with_fee(amount));
+        let swap_id = contract.initiate_swap(
+            &initiator,
+            &t.token,
+            &amount,
+            &BytesN::from_array(&env, &[91; 32]),
+            &3600u64,
+            &Bytes::from_slice(&env, &[18u8; 20]),
+            &BytesN::from_array(&env, &[0; 32]),
+            &fill_terms(&env, FillMode::AllowPartial, 0),
+        );
+        let reason = Symbol::new(&env, "no_delivery");
+
+        // Failures in separate windows don't add up
+        contract.slash_resolver(&t.admin, &resolver, &swap_id, &1, &reason);
+        env.ledger().with_mut(|li| li.timestamp = 1_100);
+        contract.slash_resolver(&t.admin, &resolver, &swap_id, &1, &reason);
+        let info = contract.get_resolver(&resolver);
+        assert!(info.active);
+        assert_eq!(info.recent_failures, 1);
+
+        // Reaching the threshold within a window jails the resolver
+        env.ledger().with_mut(|li| li.timestamp = 1_150);
+        contract.slash_resolver(&t.admin, &resolver, &swap_id, &1, &reason);
+        let info = contract.get_resolver(&resolver);
+        assert!(!info.active);
+        assert_eq!(info.jailed_until, 1_650);
+        assert_eq!(info.jail_reason, Symbol::new(&env, "slashed"));
+        assert_eq!(
+            contract.try_execute_partial_fill(&swap_id, &resolver, &1_000, &Vec::new(&env), &1u64),
+            Err(Ok(ContractError::ResolverNotActive))
+        );
+
+        // Topping up stake doesn't release it early
+        mint(&env, &t.native_token, &resolver, MIN_STAKE);
+        contract.increase_stake(&resolver, &MIN_STAKE);
+        assert!(!contract.get_resolver(&resolver).active);
+
+        // It stays jailed past the expiry until it unjails
+        assert_eq!(
+            contract.try_unjail(&resolver),
+            Err(Ok(ContractError::TimelockNotExpired))
+        );
+        env.ledger().with_mut(|li| li.timestamp = 1_650);
+        assert!(!contract.get_resolver(&resolver).active);
+        contract.unjail(&resolver);
+        let info = contract.get_resolver(&resolver);
+        assert!(info.active);
+        assert_eq!(info.jailed_until, 0);
+        assert_eq!(info.recent_failures, 0);
+        assert_eq!(contract.try_unjail(&resolver), Err(Ok(ContractError::NotJailed)));
+    }
+
+    #[test]
+    fn test_token_analytics() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 86_400 + 10);
+
+        let t = setup(&env);
+        let initiator = Address::generate(&env);
+        let resolver = Address::generate(&env);
+        let contract = &t.contract;
+        register_resolver(&env, &t, &resolver, MIN_STAKE);
+        let other_token = env.register_stellar_asset_contract(t.admin.clone());
+
+        let leaves = [fill_leaf(&env, &resolver, 1_000_000, 1)];
+        let (merkle_root, proofs) = build_tree(&env, &leaves);
+        let new_swap = |token: &Address, amount: i128, seed: u8| {
+            mint(&env, token, &initiator, with_fee(amount));
+            contract.initiate_swap(
+                &initiator,
+                token,
+                &amount,
+                &BytesN::from_array(&env, &[seed; 32]),
+                &3600u64,
+                &Bytes::from_slice(&env, &[20u8; 20]),
+                &merkle_root,
+                &fill_terms(&env, FillMode::AllowPartial, 0),
+            )
+        };
+
+        // Day 1: a filled swap in each token
+        let swap_id = new_swap(&t.token, 2_000_000, 100);
+        contract.execute_partial_fill(&swap_id, &resolver, &1_000_000, &proofs[0], &1u64);
+        new_swap(&other_token, 5_000, 101);
+
+        // Day 2: the first swap lapses and is refunded
+        env.ledger().with_mut(|li| li.timestamp = 2 * 86_400);
+        new_swap(&t.token, 3_000_000, 102);
+        contract.refund_swap(&swap_id);
+
+        let totals = contract.get_token_analytics(&t.token);
+        assert_eq!(totals.volume, 5_000_000);
+        assert_eq!(totals.swaps, 2);
+        assert_eq!(totals.fills, 1);
+        assert_eq!(totals.refunds, 1);
+        // The refunded fill's reward never left escrow
+        assert_eq!(totals.fees, 0);
+        assert_eq!(contract.get_token_analytics(&other_token).volume, 5_000);
+
+        let buckets = contract.get_daily_analytics(&t.token, &0, &2);
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets.get(0).unwrap().swaps, 0);
+        let day1 = buckets.get(1).unwrap();
+        assert_eq!((day1.volume, day1.swaps, day1.fills, day1.refunds), (2_000_000, 1, 1, 0));
+        let day2 = buckets.get(2).unwrap();
+        assert_eq!((day2.volume, day2.swaps, day2.fills, day2.refunds), (3_000_000, 1, 0, 1));
+
+        // Ranges are bounded
+        assert_eq!(
+            contract.try_get_daily_analytics(&t.token, &2, &1),
+            Err(Ok(ContractError::LimitExceeded))
+        );
+        assert_eq!(
+            contract.try_get_daily_analytics(&t.token, &0, &31),
+            Err(Ok(ContractError::LimitExceeded))
+        );
+
+        // Buckets past the most recent 30 active days move to temporary storage
+        for day in 3..32u64 {
+            env.ledger().with_mut(|li| li.timestamp = day * 86_400);
+            new_swap(&t.token, 1_000, day as u8 + 100);
+        }
+        let key = StorageKey::DailyAnalytics(t.token.clone(), 1);
+        env.as_contract(&contract.address, || {
+            assert!(!env.storage().persistent().has(&key));
+            assert!(env.storage().temporary().has(&key));
+            assert!(env
+                .storage()
+                .persistent()
+                .has(&StorageKey::DailyAnalytics(t.token.clone(), 2)));
         });
-        assert!(result.is_err());
+        let archived = contract.get_daily_analytics(&t.token, &1, &1).get(0).unwrap();
+        assert_eq!(archived.volume, 2_000_000);
+        assert_eq!(contract.get_token_analytics(&t.token).swaps, 31);
+
+        // A full range spans archived and recent buckets
+        let buckets = contract.get_daily_analytics(&t.token, &1, &31);
+        assert_eq!(buckets.len(), 31);
+        assert_eq!(buckets.get(0).unwrap().volume, 2_000_000);
+        assert_eq!(buckets.get(30).unwrap().volume, 1_000);
     }
 }
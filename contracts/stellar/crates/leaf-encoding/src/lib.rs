@@ -0,0 +1,63 @@
+#![no_std]
+
+// Leaf and node encodings shared by the bridge contract and off-chain tree
+// builders, so proofs built off-chain always match what the contract checks.
+// Callers hash the encoded bytes with keccak256.
+
+#[cfg(feature = "std")]
+extern crate std;
+
+// Merkle tree formats recorded on each swap. Legacy trees use leaves as-is;
+// current trees double hash them (OpenZeppelin style) so an internal node,
+// the hash of 64 bytes, can never pass as a leaf.
+pub const MERKLE_TREE_LEGACY: u32 = 0;
+pub const MERKLE_TREE_VERSION: u32 = 1;
+
+// Byte buffer the encodings are written into
+pub trait LeafSink {
+    fn append(&mut self, bytes: &[u8]);
+}
+
+#[cfg(feature = "std")]
+impl LeafSink for std::vec::Vec<u8> {
+    fn append(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+#[cfg(feature = "soroban")]
+impl LeafSink for soroban_sdk::Bytes {
+    fn append(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+// Fill leaf: xdr(resolver) || be(amount) || be(nonce). `sink` must already
+// hold the resolver's ScVal XDR encoding.
+pub fn encode_fill_fields<S: LeafSink>(sink: &mut S, amount: i128, nonce: u64) {
+    sink.append(&amount.to_be_bytes());
+    sink.append(&nonce.to_be_bytes());
+}
+
+// Secret leaf: be(u64 index) || secret_hash, as in Fusion+
+pub fn encode_secret_leaf<S: LeafSink>(sink: &mut S, index: u32, secret_hash: &[u8; 32]) {
+    sink.append(&(index as u64).to_be_bytes());
+    sink.append(secret_hash);
+}
+
+// Internal node: the two children in ascending order, matching MerkleProof.sol
+pub fn encode_node<S: LeafSink>(sink: &mut S, a: &[u8; 32], b: &[u8; 32]) {
+    let (first, second) = if a < b { (a, b) } else { (b, a) };
+    sink.append(first);
+    sink.append(second);
+}
+
+// Whether a tree version hashes leaves once more before pairing them;
+// None for unknown versions
+pub fn double_hashes_leaves(version: u32) -> Option<bool> {
+    match version {
+        MERKLE_TREE_LEGACY => Some(false),
+        MERKLE_TREE_VERSION => Some(true),
+        _ => None,
+    }
+}
@@ -0,0 +1,412 @@
+// Off-chain merkle tree builder for the Stellar bridge contract. Builds the
+// sorted-pair keccak trees that partial_fills::verify_merkle_proof checks,
+// from fill leaves or secret hashes, and produces JSON-serializable proofs.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use stellar_bridge_leaves as leaves;
+use stellar_xdr::curr::{Limits, ScAddress, ScVal, WriteXdr};
+
+pub use stellar_bridge_leaves::{MERKLE_TREE_LEGACY, MERKLE_TREE_VERSION};
+
+pub type Hash = [u8; 32];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    // Resolver is not a valid G... or C... strkey
+    InvalidAddress(String),
+    UnsupportedVersion(u32),
+    EmptyTree,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidAddress(address) => write!(f, "invalid resolver address: {address}"),
+            Error::UnsupportedVersion(version) => write!(f, "unsupported merkle tree version: {version}"),
+            Error::EmptyTree => write!(f, "a tree needs at least one leaf"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub fn keccak256(data: &[u8]) -> Hash {
+    Keccak256::digest(data).into()
+}
+
+// One leaf of a swap's merkle tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Leaf {
+    // Committed fill for execute_partial_fill / place_bid
+    Fill {
+        resolver: String,
+        #[serde(with = "decimal")]
+        amount: i128,
+        nonce: u64,
+    },
+    // Segment secret of a multi-secret swap, for withdraw_fill
+    Secret {
+        index: u32,
+        #[serde(with = "hex_hash")]
+        secret_hash: Hash,
+    },
+}
+
+impl Leaf {
+    // Secret leaves for a multi-secret swap, indexed in order
+    pub fn secrets(secret_hashes: &[Hash]) -> Vec<Leaf> {
+        secret_hashes
+            .iter()
+            .enumerate()
+            .map(|(index, secret_hash)| Leaf::Secret {
+                index: index as u32,
+                secret_hash: *secret_hash,
+            })
+            .collect()
+    }
+
+    // The leaf value the contract computes (fill_leaf / secret_leaf)
+    pub fn hash(&self) -> Result<Hash, Error> {
+        let mut data = Vec::new();
+        match self {
+            Leaf::Fill {
+                resolver,
+                amount,
+                nonce,
+            } => {
+                data = resolver_xdr(resolver)?;
+                leaves::encode_fill_fields(&mut data, *amount, *nonce);
+            }
+            Leaf::Secret { index, secret_hash } => {
+                leaves::encode_secret_leaf(&mut data, *index, secret_hash);
+            }
+        }
+        Ok(keccak256(&data))
+    }
+}
+
+// ScVal XDR of a resolver address, as produced by Address::to_xdr on-chain
+pub fn resolver_xdr(resolver: &str) -> Result<Vec<u8>, Error> {
+    let address =
+        ScAddress::from_str(resolver).map_err(|_| Error::InvalidAddress(resolver.to_string()))?;
+    ScVal::Address(address)
+        .to_xdr(Limits::none())
+        .map_err(|_| Error::InvalidAddress(resolver.to_string()))
+}
+
+pub fn hash_leaf(version: u32, leaf: &Hash) -> Result<Hash, Error> {
+    match leaves::double_hashes_leaves(version) {
+        Some(true) => Ok(keccak256(leaf)),
+        Some(false) => Ok(*leaf),
+        None => Err(Error::UnsupportedVersion(version)),
+    }
+}
+
+pub fn hash_pair(a: &Hash, b: &Hash) -> Hash {
+    let mut data = Vec::with_capacity(64);
+    leaves::encode_node(&mut data, a, b);
+    keccak256(&data)
+}
+
+// Mirrors partial_fills::verify_merkle_proof
+pub fn verify(version: u32, proof: &[Hash], root: &Hash, leaf: &Hash) -> bool {
+    let mut computed = match hash_leaf(version, leaf) {
+        Ok(hash) => hash,
+        Err(_) => return false,
+    };
+    for element in proof {
+        computed = hash_pair(&computed, element);
+    }
+    computed == *root
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree {
+    version: u32,
+    leaves: Vec<Hash>,
+    // levels[0] holds the hashed leaves, the last level the root; an odd
+    // node is promoted to the next level unchanged
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: &[Leaf]) -> Result<Self, Error> {
+        let hashes = leaves.iter().map(Leaf::hash).collect::<Result<Vec<_>, _>>()?;
+        Self::from_leaf_hashes(MERKLE_TREE_VERSION, hashes)
+    }
+
+    // Builds a tree over precomputed leaf values, e.g. for legacy swaps
+    pub fn from_leaf_hashes(version: u32, leaves: Vec<Hash>) -> Result<Self, Error> {
+        if leaves.is_empty() {
+            return Err(Error::EmptyTree);
+        }
+
+        let mut level = leaves
+            .iter()
+            .map(|leaf| hash_leaf(version, leaf))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut levels = Vec::new();
+        while level.len() > 1 {
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(level);
+            level = next;
+        }
+        levels.push(level);
+
+        Ok(MerkleTree {
+            version,
+            leaves,
+            levels,
+        })
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn root(&self) -> Hash {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn proof(&self, index: usize) -> Option<Vec<Hash>> {
+        if index >= self.leaves.len() {
+            return None;
+        }
+
+        let mut proof = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(position ^ 1) {
+                proof.push(*sibling);
+            }
+            position /= 2;
+        }
+        Some(proof)
+    }
+
+    pub fn leaf_proof(&self, index: usize) -> Option<LeafProof> {
+        let proof = self.proof(index)?;
+        Some(LeafProof {
+            version: self.version,
+            root: self.root(),
+            leaf: self.leaves[index],
+            proof,
+        })
+    }
+}
+
+// A proof in the shape the contract entrypoints take
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafProof {
+    pub version: u32,
+    #[serde(with = "hex_hash")]
+    pub root: Hash,
+    #[serde(with = "hex_hash")]
+    pub leaf: Hash,
+    #[serde(with = "hex_hashes")]
+    pub proof: Vec<Hash>,
+}
+
+impl LeafProof {
+    pub fn verify(&self) -> bool {
+        verify(self.version, &self.proof, &self.root, &self.leaf)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+// i128 amounts as decimal strings; JSON numbers can't hold them losslessly
+mod decimal {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &i128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i128, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
+
+// 0x-prefixed hex, as in the shared test vectors
+mod hex_hash {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(hash)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse(&value).map_err(D::Error::custom)
+    }
+
+    pub fn parse(value: &str) -> Result<[u8; 32], String> {
+        let bytes = hex::decode(value.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+        bytes.try_into().map_err(|_| "expected 32 bytes".to_string())
+    }
+}
+
+mod hex_hashes {
+    use serde::{de::Error as _, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(hashes: &[[u8; 32]], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(hashes.len()))?;
+        for hash in hashes {
+            seq.serialize_element(&format!("0x{}", hex::encode(hash)))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<[u8; 32]>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|value| super::hex_hash::parse(value).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    const VECTORS: &str = include_str!("../../../../test-vectors/merkle.json");
+    const RESOLVER: &str = "GAAZI4TCR3TY5OJHCTJC2A4QSY6CJWJH5IAJTGKIN2ER7LBNVKOCCWN7";
+
+    fn parse(value: &Value) -> Hash {
+        hex_hash::parse(value.as_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_matches_shared_vectors() {
+        let vectors: Value = serde_json::from_str(VECTORS).unwrap();
+
+        for tree in vectors["trees"].as_array().unwrap() {
+            let leaves: Vec<Hash> = tree["leaves"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|leaf| parse(&leaf["leafData"]))
+                .collect();
+            let built = MerkleTree::from_leaf_hashes(MERKLE_TREE_VERSION, leaves).unwrap();
+            assert_eq!(built.root(), parse(&tree["root"]));
+
+            for (i, leaf) in tree["leaves"].as_array().unwrap().iter().enumerate() {
+                let expected: Vec<Hash> =
+                    leaf["proof"].as_array().unwrap().iter().map(parse).collect();
+                assert_eq!(built.proof(i).unwrap(), expected);
+                assert!(built.leaf_proof(i).unwrap().verify());
+            }
+        }
+
+        for case in vectors["invalid"].as_array().unwrap() {
+            let proof: Vec<Hash> = case["proof"].as_array().unwrap().iter().map(parse).collect();
+            assert!(!verify(MERKLE_TREE_VERSION, &proof, &parse(&case["root"]), &parse(&case["leafData"])));
+        }
+    }
+
+    #[test]
+    fn test_fill_and_secret_trees() {
+        let fills = [
+            Leaf::Fill {
+                resolver: RESOLVER.to_string(),
+                amount: 3_000,
+                nonce: 1,
+            },
+            Leaf::Fill {
+                resolver: RESOLVER.to_string(),
+                amount: 2_000,
+                nonce: 2,
+            },
+            Leaf::Fill {
+                resolver: RESOLVER.to_string(),
+                amount: 5_000,
+                nonce: 3,
+            },
+        ];
+        let tree = MerkleTree::new(&fills).unwrap();
+        assert_eq!(tree.len(), 3);
+        for (i, fill) in fills.iter().enumerate() {
+            let proof = tree.leaf_proof(i).unwrap();
+            assert_eq!(proof.leaf, fill.hash().unwrap());
+            assert!(proof.verify());
+        }
+        assert!(tree.proof(3).is_none());
+
+        // A proof only verifies its own leaf
+        let proof = tree.proof(0).unwrap();
+        assert!(!verify(MERKLE_TREE_VERSION, &proof, &tree.root(), &fills[1].hash().unwrap()));
+
+        let secrets = Leaf::secrets(&[keccak256(b"secret-0"), keccak256(b"secret-1")]);
+        let tree = MerkleTree::new(&secrets).unwrap();
+        assert!(tree.leaf_proof(1).unwrap().verify());
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let leaf = Leaf::Fill {
+            resolver: "not-an-address".to_string(),
+            amount: 1,
+            nonce: 1,
+        };
+        assert!(matches!(MerkleTree::new(&[leaf]), Err(Error::InvalidAddress(_))));
+        assert_eq!(MerkleTree::new(&[]), Err(Error::EmptyTree));
+        assert_eq!(
+            MerkleTree::from_leaf_hashes(2, vec![[0; 32]]),
+            Err(Error::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn test_proof_json_round_trip() {
+        let tree = MerkleTree::new(&Leaf::secrets(&[[1; 32], [2; 32], [3; 32]])).unwrap();
+        let proof = tree.leaf_proof(2).unwrap();
+
+        let json = proof.to_json().unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["root"], format!("0x{}", hex::encode(tree.root())));
+
+        assert_eq!(LeafProof::from_json(&json).unwrap(), proof);
+
+        let leaf: Leaf = serde_json::from_str(
+            r#"{"type": "fill", "resolver": "GAAZI4TCR3TY5OJHCTJC2A4QSY6CJWJH5IAJTGKIN2ER7LBNVKOCCWN7", "amount": "5", "nonce": 9}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            leaf,
+            Leaf::Fill {
+                resolver: RESOLVER.to_string(),
+                amount: 5,
+                nonce: 9,
+            }
+        );
+    }
+}
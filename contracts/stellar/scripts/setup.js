@@ -230,8 +230,15 @@ async function createTestSwap(userKeypair, amount, ethAddress) {
                 secretHash,
                 timelock,
                 Buffer.from(ethAddress.slice(2), 'hex'), // ethereum address
-                true, // partial fills enabled
                 merkleRoot,
+                {
+                    fill_mode: ['AllowPartial'],
+                    secret_parts: 0, // single secret
+                    min_fill_amount: 0,
+                    fill_step: null,
+                    exclusive_resolvers: [], // open to every resolver
+                    exclusivity_deadline: 0,
+                },
             ],
         }))
         .setTimeout(300)